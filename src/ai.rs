@@ -0,0 +1,329 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::rng::GameRng;
+
+/// The thing, which is doing one of its behaviours at a time (see `Brain`)
+pub trait Agent: 'static {
+    /// the name of the behaviour, the current one is stored in the agent
+    type Kind: Copy + PartialEq + Send + Sync + 'static;
+    /// everything, what the behaviours can see and change in one frame
+    type Ctx<'a>;
+
+    fn current<'c>(ctx: &'c mut Self::Ctx<'_>) -> &'c mut Self::Kind;
+
+    fn rng<'c>(ctx: &'c mut Self::Ctx<'_>) -> &'c mut GameRng;
+}
+
+/// How much the agent wants to start the behaviour
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Utility {
+    /// the behaviour can't be started now
+    None,
+    /// it is taken randomly by its weight, after the current behaviour is finished
+    Weight(u32),
+    /// it is interrupting the current behaviour, when that one is less urgent
+    Urgent(u32),
+}
+
+impl Utility {
+    fn urgency(self) -> Option<u32> {
+        match self {
+            Utility::Urgent(urgency) => Some(urgency),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Running,
+    Finished,
+}
+
+pub trait Behaviour<A: Agent>: Send + Sync + 'static {
+    /// The entry condition, it is asked once per frame after the current behaviour was ticked
+    fn utility(&self, ctx: &mut A::Ctx<'_>) -> Utility;
+
+    fn enter(&self, _ctx: &mut A::Ctx<'_>) {}
+
+    /// The other behaviour is taken instead, or this one is started again
+    fn exit(&self, _ctx: &mut A::Ctx<'_>) {}
+
+    /// Does the behaviour for one frame, the exit condition is the returned Finished
+    fn tick(&self, ctx: &mut A::Ctx<'_>) -> Status;
+}
+
+/// The utility AI: the current behaviour is ticked every frame until it is finished, then the
+/// most urgent behaviour or a random one by the weights is taken. The urgent behaviours are not
+/// waiting for it.
+///
+/// The new behaviours are added with `add`, also from the other plugins. The brain is using the
+/// GameRng, so the systems calling `think` must be ordered like the other ones using it
+#[derive(Resource)]
+pub struct Brain<A: Agent> {
+    behaviours: Vec<(A::Kind, Box<dyn Behaviour<A>>)>,
+}
+
+impl<A: Agent> Default for Brain<A> {
+    fn default() -> Self {
+        Self {
+            behaviours: Vec::new(),
+        }
+    }
+}
+
+impl<A: Agent> Brain<A> {
+    /// The behaviour added earlier wins, when they are equally urgent
+    pub fn add(&mut self, kind: A::Kind, behaviour: impl Behaviour<A>) -> &mut Self {
+        self.behaviours.push((kind, Box::new(behaviour)));
+        self
+    }
+
+    pub fn think(&self, ctx: &mut A::Ctx<'_>) {
+        let current = *A::current(ctx);
+        let current_i = self
+            .behaviours
+            .iter()
+            .position(|(kind, _)| *kind == current);
+        // the unknown behaviour (from an old save) is changed right away
+        let status = current_i.map_or(Status::Finished, |i| self.behaviours[i].1.tick(ctx));
+
+        let utilities: Vec<Utility> = self
+            .behaviours
+            .iter()
+            .map(|(_, behaviour)| behaviour.utility(ctx))
+            .collect();
+        // the first one of the most urgent behaviours
+        let most_urgent = utilities
+            .iter()
+            .enumerate()
+            .rev()
+            .filter_map(|(i, utility)| utility.urgency().map(|urgency| (i, urgency)))
+            .max_by_key(|(_, urgency)| *urgency);
+        let current_urgency = current_i.and_then(|i| utilities[i].urgency());
+
+        let next = match (status, most_urgent) {
+            (Status::Finished, Some((i, _))) => Some(i),
+            (Status::Finished, None) => Self::choose_by_weight(&utilities, ctx),
+            // the None is less than any urgency
+            (Status::Running, Some((i, urgency)))
+                if Some(i) != current_i && current_urgency < Some(urgency) =>
+            {
+                Some(i)
+            }
+            (Status::Running, _) => None,
+        };
+
+        if let Some(i) = next {
+            if let Some(current_i) = current_i {
+                self.behaviours[current_i].1.exit(ctx);
+            }
+            let (kind, behaviour) = &self.behaviours[i];
+            *A::current(ctx) = *kind;
+            behaviour.enter(ctx);
+        }
+    }
+
+    fn choose_by_weight(utilities: &[Utility], ctx: &mut A::Ctx<'_>) -> Option<usize> {
+        let weight = |utility: &Utility| match utility {
+            Utility::Weight(weight) => *weight,
+            _ => 0,
+        };
+        let total: u32 = utilities.iter().map(weight).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut roll = A::rng(ctx).gen_range(0..total);
+        utilities.iter().position(|utility| {
+            if roll < weight(utility) {
+                true
+            } else {
+                roll -= weight(utility);
+                false
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Kind {
+        Walk,
+        Run,
+        Flee,
+        Hide,
+    }
+
+    /// The behaviours are doing, what the test is telling them, and the calls are written down
+    struct Dummy;
+
+    struct DummyCtx {
+        current: Kind,
+        utilities: Vec<(Kind, Utility)>,
+        finished: Vec<Kind>,
+        calls: Vec<String>,
+        rng: GameRng,
+    }
+
+    impl DummyCtx {
+        fn new(current: Kind, utilities: &[(Kind, Utility)]) -> Self {
+            Self {
+                current,
+                utilities: utilities.to_vec(),
+                finished: Vec::new(),
+                calls: Vec::new(),
+                rng: GameRng::from_seed(0),
+            }
+        }
+    }
+
+    impl Agent for Dummy {
+        type Kind = Kind;
+        type Ctx<'a> = DummyCtx;
+
+        fn current<'c>(ctx: &'c mut DummyCtx) -> &'c mut Kind {
+            &mut ctx.current
+        }
+
+        fn rng<'c>(ctx: &'c mut DummyCtx) -> &'c mut GameRng {
+            &mut ctx.rng
+        }
+    }
+
+    struct Scripted(Kind);
+
+    impl Behaviour<Dummy> for Scripted {
+        fn utility(&self, ctx: &mut DummyCtx) -> Utility {
+            ctx.utilities
+                .iter()
+                .find(|(kind, _)| *kind == self.0)
+                .map_or(Utility::None, |(_, utility)| *utility)
+        }
+
+        fn enter(&self, ctx: &mut DummyCtx) {
+            ctx.calls.push(format!("enter {:?}", self.0));
+        }
+
+        fn exit(&self, ctx: &mut DummyCtx) {
+            ctx.calls.push(format!("exit {:?}", self.0));
+        }
+
+        fn tick(&self, ctx: &mut DummyCtx) -> Status {
+            ctx.calls.push(format!("tick {:?}", self.0));
+            if ctx.finished.contains(&self.0) {
+                Status::Finished
+            } else {
+                Status::Running
+            }
+        }
+    }
+
+    fn brain() -> Brain<Dummy> {
+        let mut brain = Brain::default();
+        for kind in [Kind::Walk, Kind::Run, Kind::Flee, Kind::Hide] {
+            brain.add(kind, Scripted(kind));
+        }
+        brain
+    }
+
+    #[test]
+    fn urgent_wins_over_weight() {
+        let mut ctx = DummyCtx::new(
+            Kind::Walk,
+            &[
+                (Kind::Run, Utility::Weight(1000)),
+                (Kind::Flee, Utility::Urgent(1)),
+            ],
+        );
+        ctx.finished.push(Kind::Walk);
+
+        brain().think(&mut ctx);
+
+        assert_eq!(ctx.current, Kind::Flee);
+    }
+
+    #[test]
+    fn urgent_interrupts_the_running_behaviour() {
+        let mut ctx = DummyCtx::new(Kind::Walk, &[(Kind::Flee, Utility::Urgent(1))]);
+
+        brain().think(&mut ctx);
+
+        assert_eq!(ctx.current, Kind::Flee);
+        assert_eq!(ctx.calls, ["tick Walk", "exit Walk", "enter Flee"]);
+    }
+
+    #[test]
+    fn weight_waits_for_the_running_behaviour() {
+        let mut ctx = DummyCtx::new(
+            Kind::Walk,
+            &[
+                (Kind::Walk, Utility::Weight(1)),
+                (Kind::Run, Utility::Weight(1000)),
+            ],
+        );
+
+        brain().think(&mut ctx);
+
+        assert_eq!(ctx.current, Kind::Walk);
+        assert_eq!(ctx.calls, ["tick Walk"]);
+    }
+
+    #[test]
+    fn more_urgent_wins_and_the_first_one_of_equal_ones() {
+        let mut ctx = DummyCtx::new(
+            Kind::Walk,
+            &[
+                (Kind::Run, Utility::Urgent(1)),
+                (Kind::Flee, Utility::Urgent(2)),
+                (Kind::Hide, Utility::Urgent(2)),
+            ],
+        );
+
+        brain().think(&mut ctx);
+
+        assert_eq!(ctx.current, Kind::Flee);
+    }
+
+    #[test]
+    fn equally_urgent_does_not_interrupt() {
+        let mut ctx = DummyCtx::new(
+            Kind::Hide,
+            &[
+                (Kind::Flee, Utility::Urgent(2)),
+                (Kind::Hide, Utility::Urgent(2)),
+            ],
+        );
+
+        brain().think(&mut ctx);
+
+        assert_eq!(ctx.current, Kind::Hide);
+        assert_eq!(ctx.calls, ["tick Hide"]);
+    }
+
+    #[test]
+    fn finished_behaviour_is_replaced_by_weight() {
+        let mut ctx = DummyCtx::new(Kind::Walk, &[(Kind::Run, Utility::Weight(1))]);
+        ctx.finished.push(Kind::Walk);
+
+        brain().think(&mut ctx);
+
+        assert_eq!(ctx.current, Kind::Run);
+        assert_eq!(ctx.calls, ["tick Walk", "exit Walk", "enter Run"]);
+    }
+
+    #[test]
+    fn finished_behaviour_without_anything_else_is_kept() {
+        let mut ctx = DummyCtx::new(Kind::Walk, &[]);
+        ctx.finished.push(Kind::Walk);
+
+        brain().think(&mut ctx);
+
+        assert_eq!(ctx.current, Kind::Walk);
+        assert_eq!(ctx.calls, ["tick Walk"]);
+    }
+}
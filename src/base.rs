@@ -1,20 +1,43 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::settings::*;
+use crate::{
+    config::{config_reloaded, GameConfig},
+    spawning::{radius_mesh, spawn_player_base},
+    state::{GameState, InRound, SpawnSet},
+};
+
+pub struct BasePlugin;
+
+impl Plugin for BasePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>();
+
+        app.add_systems(
+            OnEnter(InRound),
+            spawn_player_base.in_set(SpawnSet::Entities),
+        );
+        app.add_systems(
+            Update,
+            change_base_text.run_if(in_state(GameState::Playing)),
+        );
+        app.add_systems(Update, apply_config_to_bases.run_if(config_reloaded));
+    }
+}
 
 #[derive(Component, Default)]
 pub struct BaseCatchingRadius;
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Base {
     pub radius: f32,
     pub chickens_amount: usize,
 }
 
-impl Default for Base {
-    fn default() -> Self {
+impl Base {
+    pub fn from_config(config: &GameConfig) -> Self {
         Self {
-            radius: BASE_CATCHING_RADIUS,
+            radius: config.base.catching_radius,
             chickens_amount: 100,
         }
     }
@@ -27,16 +50,20 @@ pub struct BaseBundle {
 }
 
 impl BaseBundle {
-    pub fn default_on_point(spawnpoint: Vec2) -> Self {
+    pub fn default_on_point(spawnpoint: Vec2, config: &GameConfig) -> Self {
+        Self::new(Base::from_config(config), spawnpoint, config)
+    }
+
+    pub fn new(base: Base, spawnpoint: Vec2, config: &GameConfig) -> Self {
         Self {
-            base: Default::default(),
+            base,
             sprite_bundle: SpriteBundle {
                 sprite: Sprite {
-                    color: BASE_COLOR,
-                    custom_size: Some(Vec2::new(BASE_SIZE, BASE_SIZE)),
+                    color: config.base.color,
+                    custom_size: Some(Vec2::new(config.base.size, config.base.size)),
                     ..Default::default()
                 },
-                transform: Transform::from_translation(spawnpoint.extend(BASE_Z)),
+                transform: Transform::from_translation(spawnpoint.extend(config.base.z)),
                 ..Default::default()
             },
         }
@@ -61,3 +88,26 @@ pub fn change_base_text(base_q: Query<&Base>, mut text_q: Query<(&mut Text, &Bel
         text.sections[0].value = chickens_count.to_string();
     }
 }
+
+pub fn apply_config_to_bases(
+    mut commands: Commands,
+    mut bases_q: Query<(&mut Base, &mut Sprite)>,
+    radius_q: Query<Entity, With<BaseCatchingRadius>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut material: ResMut<Assets<ColorMaterial>>,
+    config: Res<GameConfig>,
+) {
+    for (mut base, mut sprite) in bases_q.iter_mut() {
+        base.radius = config.base.catching_radius;
+        sprite.color = config.base.color;
+        sprite.custom_size = Some(Vec2::new(config.base.size, config.base.size));
+    }
+
+    let mesh = radius_mesh(&mut meshes, config.base.catching_radius);
+    let color = material.add(config.base.catching_radius_color);
+    for radius_ent in radius_q.iter() {
+        commands
+            .entity(radius_ent)
+            .insert((mesh.clone(), color.clone()));
+    }
+}
@@ -1,20 +1,48 @@
-use bevy::math::NormedVectorSpace;
 use bevy::prelude::*;
 
+use crate::config::GameConfig;
 use crate::player::Player;
-use crate::settings::*;
+use crate::state::{GameState, InRound};
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>();
+
+        app.add_systems(Startup, spawn_camera);
+        app.add_systems(OnEnter(InRound), reset_camera);
+        app.add_systems(Update, move_camera.run_if(in_state(GameState::Playing)));
+        // the editor is moving the camera by itself, but the zoom is the same
+        app.add_systems(
+            Update,
+            zoom_camera.run_if(in_state(GameState::Playing).or_else(in_state(GameState::Editor))),
+        );
+    }
+}
 
 #[derive(Component)]
 pub struct MainCamera;
 
 pub fn spawn_camera(mut commands: Commands) {
-    commands.spawn((Camera2dBundle::default(), MainCamera));
+    // the ui is on the main camera, not on the minimap
+    commands.spawn((Camera2dBundle::default(), MainCamera, IsDefaultUiCamera));
+}
+
+// the player is spawned in the center of the map
+pub fn reset_camera(
+    mut camera_q: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    let (mut c_pos, mut projection) = camera_q.single_mut();
+    c_pos.translation = Vec3::new(0., 0., c_pos.translation.z);
+    projection.scale = 1.;
 }
 
 pub fn move_camera(
     mut camera_q: Query<&mut Transform, (With<MainCamera>, Without<Player>)>,
     player_q: Query<&Transform, With<Player>>,
     time: Res<Time>,
+    config: Res<GameConfig>,
 ) {
     let mut c_pos = camera_q.single_mut();
     let p_pos = player_q.single();
@@ -22,10 +50,12 @@ pub fn move_camera(
     let move_dir = (p_pos.translation - c_pos.translation).xy();
 
     let distance = move_dir.length();
-    if distance > MIN_CAMERA_DISTANCE_TO_PLAYER {
-        let speed = (distance.clamp(MIN_CAMERA_DISTANCE_TO_PLAYER, MAX_CAMERA_DISTANCE_TO_PLAYER)
-            / MAX_CAMERA_DISTANCE_TO_PLAYER)
-            * MAX_CAMERA_SPEED;
+    if distance > config.camera.min_distance_to_player {
+        let speed = (distance.clamp(
+            config.camera.min_distance_to_player,
+            config.camera.max_distance_to_player,
+        ) / config.camera.max_distance_to_player)
+            * config.camera.max_speed;
 
         c_pos.translation += (move_dir.normalize() * speed * time.delta_seconds()).extend(0.);
     }
@@ -35,28 +65,15 @@ pub fn zoom_camera(
     mut camera_q: Query<&mut OrthographicProjection, With<MainCamera>>,
     input: Res<ButtonInput<KeyCode>>,
     time: Res<Time>,
+    config: Res<GameConfig>,
 ) {
     let mut projection = camera_q.get_single_mut().unwrap();
 
-    if input.pressed(ZOOM_OUT_KEY) {
-        projection.scale -= ZOOM_SPEED * time.delta_seconds();
-    }
-
-    if input.pressed(ZOOM_IN_KEY) {
-        projection.scale += ZOOM_SPEED * time.delta_seconds();
+    if input.pressed(config.camera.zoom_out_key) {
+        projection.scale -= config.camera.zoom_speed * time.delta_seconds();
     }
-}
 
-// for now we have:
-// werewolfes, chickens, player, bases, corrals
-//  we dont want that the player can see chickens, werewolfes and corrals if they are not nesr to
-//  the player
-pub fn toggle_visability(
-    mut sprites_q: Query<(&Transform, &mut Sprite), Without<Player>>,
-    player_q: Query<&Transform, With<Player>>,
-) {
-    let p_pos = player_q.get_single().unwrap();
-    for (s_pos, sprite) in sprites_q.iter_mut() {
-        if s_pos.translation.xy().distance(p_pos.translation.xy()) >= PLAYER_SIGHT_DISTANCE {}
+    if input.pressed(config.camera.zoom_in_key) {
+        projection.scale += config.camera.zoom_speed * time.delta_seconds();
     }
 }
@@ -1,58 +1,215 @@
 use crate::{
-    chicken_corral::{ChickenCorral, ChickenCorralWall},
+    chicken_corral::ChickenCorral,
+    config::{config_reloaded, GameConfig},
+    flocking::flock_chickens,
+    map::MapLayout,
     misc::get_random_dir,
-    settings::*,
+    player::{ForPlayerCatchable, KnockedOut, Player, Slowed},
+    rng::GameRng,
+    spawning::spawn_chicken_in_corrals,
+    state::GameState,
+    werewolf::Werewolf,
+    Game,
 };
 use bevy::prelude::*;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
-#[derive(Default)]
+pub struct ChickenPlugin;
+
+impl Plugin for ChickenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>();
+        app.init_resource::<Game>();
+        app.init_resource::<GameRng>();
+        app.init_resource::<MapLayout>();
+
+        app.add_systems(
+            Update,
+            // the random numbers must be taken always in the same order
+            (behave_chickens, flock_chickens, spawn_chicken_in_corrals)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+        app.add_systems(Update, apply_config_to_chickens.run_if(config_reloaded));
+    }
+}
+
+#[derive(Default, Serialize, Deserialize, Clone)]
 enum ChickenBehaviour {
     Calm,
     Mad,
     #[default]
     Idle,
+    /// the chicken is chasing and pecking the player, it can be catched only after the struggle
+    Aggressive {
+        struggle_left: usize,
+        peck_timer: Timer,
+    },
+    /// the chicken is running away from the player and the werewolves, until it doesn't see
+    /// them for a while
+    Panic {
+        timer: Timer,
+    },
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Chicken {
     behaviour_change_timer: Timer,
     behaviour: ChickenBehaviour,
     move_dir: Option<Vec2>,
+    /// after the panic the chicken is tired and can't panic again, until it is finished
+    panic_cooldown: Timer,
+    /// the behaviour is setting the wanted velocity, the flock is steering it (see flocking.rs)
+    pub velocity: Vec2,
 }
 
 impl Chicken {
-    fn change_behaviour_to(&mut self, next_beh: ChickenBehaviour) {
+    /// Turns the chicken back, when it came to the edge of the arena
+    pub fn bounce_off_edge(&mut self, x: bool, y: bool) {
+        if let Some(dir) = self.move_dir.as_mut() {
+            if x {
+                dir.x = -dir.x;
+            }
+            if y {
+                dir.y = -dir.y;
+            }
+        }
+    }
+
+    /// The panicking chicken is not bouncing off the walls, it is only sliding along them, so it
+    /// can be cornered
+    pub fn bounce_off(&mut self, normal: Vec2) {
+        if self.is_panicking() {
+            return;
+        }
+        if let Some(dir) = self.move_dir.as_mut() {
+            if dir.dot(normal) < 0. {
+                *dir -= 2. * dir.dot(normal) * normal;
+            }
+        }
+    }
+
+    fn change_behaviour_to(&mut self, next_beh: ChickenBehaviour, rng: &mut GameRng) {
         match next_beh {
             ChickenBehaviour::Calm => {
                 self.behaviour = ChickenBehaviour::Calm;
-                self.move_dir = Some(get_random_dir());
+                self.move_dir = Some(get_random_dir(rng));
             }
             ChickenBehaviour::Mad => {
                 self.behaviour = ChickenBehaviour::Mad;
-                self.move_dir = Some(get_random_dir());
+                self.move_dir = Some(get_random_dir(rng));
             }
             ChickenBehaviour::Idle => {
                 self.behaviour = ChickenBehaviour::Idle;
                 self.move_dir = None;
             }
+            ChickenBehaviour::Aggressive { .. } => {
+                self.behaviour = next_beh;
+                self.move_dir = None;
+            }
+            // the direction is taken from the predators every frame
+            ChickenBehaviour::Panic { .. } => {
+                self.behaviour = next_beh;
+            }
+        }
+    }
+
+    pub fn is_panicking(&self) -> bool {
+        matches!(self.behaviour, ChickenBehaviour::Panic { .. })
+    }
+
+    /// The tired and the aggressive chickens are not panicking
+    pub fn can_panic(&self) -> bool {
+        self.is_panicking() || (!self.is_aggressive() && self.panic_cooldown.finished())
+    }
+
+    /// Runs away in the direction, seeing the danger again is making the panic longer
+    pub fn panic(&mut self, dir: Vec2, config: &GameConfig) {
+        self.behaviour = ChickenBehaviour::Panic {
+            timer: Timer::from_seconds(config.chicken.panic_delta, TimerMode::Once),
+        };
+        self.move_dir = Some(dir);
+    }
+
+    pub fn is_aggressive(&self) -> bool {
+        matches!(self.behaviour, ChickenBehaviour::Aggressive { .. })
+    }
+
+    /// The player tried to catch the chicken, with some chance it is fighting back
+    pub fn make_aggressive(&mut self, config: &GameConfig) {
+        self.behaviour = ChickenBehaviour::Aggressive {
+            struggle_left: config.chicken.struggle_presses,
+            peck_timer: Timer::from_seconds(config.chicken.peck_delta, TimerMode::Repeating),
+        };
+        // the direction to the player is taken every frame
+        self.move_dir = None;
+    }
+
+    /// Returns true, when the aggressive chicken has no strength anymore and can be catched
+    pub fn struggle(&mut self) -> bool {
+        match &mut self.behaviour {
+            ChickenBehaviour::Aggressive { struggle_left, .. } => {
+                *struggle_left = struggle_left.saturating_sub(1);
+                *struggle_left == 0
+            }
+            _ => true,
+        }
+    }
+
+    pub fn color(&self, config: &GameConfig) -> Color {
+        if self.is_aggressive() {
+            config.chicken.aggressive_color
+        } else {
+            config.chicken.color
+        }
+    }
+
+    /// The chicken, which the player can catch right now, is darker
+    pub fn sprite_color(&self, catchable: bool, config: &GameConfig) -> Color {
+        if catchable {
+            self.color(config).mix(&Color::BLACK, 0.5)
+        } else {
+            self.color(config)
         }
     }
 }
 
-impl Default for Chicken {
-    fn default() -> Self {
+impl Chicken {
+    pub fn from_config(config: &GameConfig) -> Self {
         Self {
             behaviour_change_timer: Timer::from_seconds(
-                CHICKEN_BEHAVIOUR_CHANGE_DELTA,
+                config.chicken.behaviour_change_delta,
                 TimerMode::Repeating,
             ),
             behaviour: ChickenBehaviour::default(),
             move_dir: None,
+            panic_cooldown: finished_timer(config.chicken.panic_cooldown),
+            velocity: Vec2::ZERO,
         }
     }
 }
 
+// the new chicken is not tired, so it can panic right away
+fn finished_timer(seconds: f32) -> Timer {
+    let mut timer = Timer::from_seconds(seconds, TimerMode::Once);
+    timer.tick(timer.duration());
+    timer
+}
+
+/// The direction away from all the predators around, the nearer ones are more important
+fn flee_dir(ch_pos: Vec2, predators: &[Vec2], config: &GameConfig) -> Option<Vec2> {
+    let away: Vec2 = predators
+        .iter()
+        .map(|pred_pos| ch_pos - *pred_pos)
+        .filter(|away| away.length() < config.chicken.flee_radius)
+        .map(|away| away.normalize_or_zero() * (config.chicken.flee_radius - away.length()))
+        .sum();
+
+    (away != Vec2::ZERO).then(|| away.normalize())
+}
+
 #[derive(Bundle)]
 pub struct ChickenBundle {
     sprite_bundle: SpriteBundle,
@@ -60,74 +217,160 @@ pub struct ChickenBundle {
 }
 
 impl ChickenBundle {
-    pub fn default_in_corral(c_pos: Vec3, corral: &ChickenCorral) -> Self {
+    pub fn default_in_corral(
+        c_pos: Vec3,
+        corral: &ChickenCorral,
+        config: &GameConfig,
+        rng: &mut GameRng,
+    ) -> Self {
+        let wall_length = config.corral.wall_length;
         // for now we pickeng just the minimum of the both sides of the corral
         let min_walls_size = corral.length.min(corral.heigth) as f32;
         // the c_pos is the center of the corral
-        let delta_spawn = rand::thread_rng()
-            .gen_range(0.0..(min_walls_size * CORRAL_WALL_LENGTH) / 2. - CORRAL_WALL_LENGTH);
-        let spawn_dir = Vec2::new(
-            rand::thread_rng().gen_range(-1.0..1.0),
-            rand::thread_rng().gen_range(-1.0..1.0),
-        );
+        let delta_spawn = rng.gen_range(0.0..(min_walls_size * wall_length) / 2. - wall_length);
+        let spawn_dir = Vec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+
+        Self::new(
+            Chicken::from_config(config),
+            c_pos + (spawn_dir * delta_spawn).extend(config.chicken.z),
+            config,
+        )
+    }
 
+    pub fn new(chicken: Chicken, pos: Vec3, config: &GameConfig) -> Self {
         Self {
             sprite_bundle: SpriteBundle {
-                transform: Transform::from_translation(
-                    c_pos + (spawn_dir * delta_spawn).extend(CHICKEN_Z),
-                ),
+                transform: Transform::from_translation(pos),
                 sprite: Sprite {
-                    color: CHICKEN_COLOR,
-                    custom_size: Some(Vec2::new(CHICKEN_SIZE, CHICKEN_SIZE)),
+                    color: chicken.color(config),
+                    custom_size: Some(Vec2::new(config.chicken.size, config.chicken.size)),
                     ..Default::default()
                 },
                 ..Default::default()
             },
-            chicken: Chicken::default(),
+            chicken,
         }
     }
 }
 
-pub fn behave_chickens(mut chickens_q: Query<(&mut Chicken, &mut Transform)>, time: Res<Time>) {
-    for (mut chicken, mut ch_pos) in chickens_q.iter_mut() {
+/// Chooses the behaviour and the wanted velocity of every chicken, it is moved in flock_chickens
+#[allow(clippy::too_many_arguments)]
+pub fn behave_chickens(
+    mut commands: Commands,
+    mut chickens_q: Query<(
+        &mut Chicken,
+        &Transform,
+        &mut Sprite,
+        Has<ForPlayerCatchable>,
+    )>,
+    player_q: Query<(Entity, &Transform, Has<KnockedOut>), (With<Player>, Without<Chicken>)>,
+    werewolves_q: Query<(&Transform, &Werewolf), Without<Chicken>>,
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+    config: Res<GameConfig>,
+) {
+    let player = player_q.get_single().ok();
+    // the knocked out player is not dangerous
+    let predators: Vec<Vec2> = player
+        .filter(|(_, _, knocked_out)| !knocked_out)
+        .map(|(_, p_pos, _)| p_pos.translation.xy())
+        .into_iter()
+        // the lurking werewolf is not moving, so the chickens don't notice it
+        .chain(
+            werewolves_q
+                .iter()
+                .filter(|(_, werewolf)| !werewolf.is_lurking())
+                .map(|(w_pos, _)| w_pos.translation.xy()),
+        )
+        .collect();
+
+    for (mut chicken, ch_pos, mut sprite, catchable) in chickens_q.iter_mut() {
         chicken.behaviour_change_timer.tick(time.delta());
+        chicken.panic_cooldown.tick(time.delta());
+
+        let flee_dir = flee_dir(ch_pos.translation.xy(), &predators, &config);
 
-        if chicken.behaviour_change_timer.finished() {
-            if rand::thread_rng().gen_ratio(5, 10) {
-                chicken.change_behaviour_to(ChickenBehaviour::Mad);
-            } else if rand::thread_rng().gen_ratio(5, 10) {
-                chicken.change_behaviour_to(ChickenBehaviour::Calm);
+        // the aggressive chicken is calming down only, when the player is away
+        if let ChickenBehaviour::Aggressive { .. } = chicken.behaviour {
+            let player_near = player.is_some_and(|(_, p_pos, knocked_out)| {
+                !knocked_out
+                    && p_pos.translation.xy().distance(ch_pos.translation.xy())
+                        < config.chicken.aggressive_give_up_distance
+            });
+            if !player_near {
+                chicken.change_behaviour_to(ChickenBehaviour::Calm, &mut rng);
+                sprite.color = chicken.sprite_color(catchable, &config);
+            }
+        } else if let Some(flee_dir) = flee_dir.filter(|_| chicken.can_panic()) {
+            chicken.panic(flee_dir, &config);
+        } else if let ChickenBehaviour::Panic { timer } = &mut chicken.behaviour {
+            if timer.tick(time.delta()).finished() {
+                chicken.panic_cooldown.reset();
+                chicken.change_behaviour_to(ChickenBehaviour::Calm, &mut rng);
+            }
+        } else if chicken.behaviour_change_timer.finished() {
+            if rng.gen_ratio(5, 10) {
+                chicken.change_behaviour_to(ChickenBehaviour::Mad, &mut rng);
+            } else if rng.gen_ratio(5, 10) {
+                chicken.change_behaviour_to(ChickenBehaviour::Calm, &mut rng);
             } else {
-                chicken.change_behaviour_to(ChickenBehaviour::Idle);
+                chicken.change_behaviour_to(ChickenBehaviour::Idle, &mut rng);
             }
         }
 
-        match chicken.behaviour {
-            ChickenBehaviour::Calm => {
-                ch_pos.translation +=
-                    chicken.move_dir.unwrap().extend(0.) * CHICKEN_CALM_SPEED * time.delta_seconds()
-            }
-            ChickenBehaviour::Mad => {
-                ch_pos.translation +=
-                    chicken.move_dir.unwrap().extend(0.) * CHICKEN_MAD_SPEED * time.delta_seconds()
+        let move_dir = chicken.move_dir.unwrap_or_default();
+        chicken.velocity = match &mut chicken.behaviour {
+            ChickenBehaviour::Calm => move_dir * config.chicken.calm_speed,
+            ChickenBehaviour::Mad => move_dir * config.chicken.mad_speed,
+            ChickenBehaviour::Idle => Vec2::ZERO, // do nothing, this is real idle :)
+            ChickenBehaviour::Panic { .. } => move_dir * config.chicken.panic_speed,
+            ChickenBehaviour::Aggressive { peck_timer, .. } => {
+                // it was calmed down above, if there is no player
+                let Some((player_ent, p_pos, _)) = player else {
+                    continue;
+                };
+                peck_timer.tick(time.delta());
+
+                let to_player = p_pos.translation.xy() - ch_pos.translation.xy();
+                if to_player.length() <= config.chicken.peck_distance {
+                    if peck_timer.finished() {
+                        commands
+                            .entity(player_ent)
+                            .insert(Slowed(Timer::from_seconds(
+                                config.chicken.peck_slow_delta,
+                                TimerMode::Once,
+                            )));
+                    }
+                    Vec2::ZERO
+                } else {
+                    // the angry chicken is jumping over the walls of the corral
+                    to_player.normalize_or_zero() * config.chicken.aggressive_speed
+                }
             }
-            ChickenBehaviour::Idle => {} // do nothing, this is real idle :)
-        }
+        };
     }
 }
 
-pub fn chicken_corral_collision(
-    mut chickens_q: Query<(&Transform, &mut Chicken)>,
-    corrals_q: Query<&Transform, (With<ChickenCorralWall>, Without<Chicken>)>,
+pub fn apply_config_to_chickens(
+    mut chickens_q: Query<(&mut Chicken, &mut Sprite)>,
+    mut game: ResMut<Game>,
+    config: Res<GameConfig>,
 ) {
-    for (ch_pos, mut ch_settings) in chickens_q.iter_mut() {
-        for co_pos in corrals_q.iter() {
-            if ch_settings.move_dir.is_some()
-                && ch_pos.translation.xy().distance(co_pos.translation.xy())
-                    <= (CORRAL_WALL_HEIGTH + CHICKEN_SIZE) / 2.
-            {
-                ch_settings.move_dir = Some(ch_settings.move_dir.unwrap() * -1.);
-            }
+    game.chicken_spawn_timer
+        .set_duration(Duration::from_secs_f32(config.chicken_spawn_delta));
+
+    for (mut chicken, mut sprite) in chickens_q.iter_mut() {
+        chicken
+            .behaviour_change_timer
+            .set_duration(Duration::from_secs_f32(
+                config.chicken.behaviour_change_delta,
+            ));
+        chicken
+            .panic_cooldown
+            .set_duration(Duration::from_secs_f32(config.chicken.panic_cooldown));
+        if let ChickenBehaviour::Aggressive { peck_timer, .. } = &mut chicken.behaviour {
+            peck_timer.set_duration(Duration::from_secs_f32(config.chicken.peck_delta));
         }
+        sprite.custom_size = Some(Vec2::new(config.chicken.size, config.chicken.size));
     }
 }
@@ -1,24 +1,77 @@
-use bevy::prelude::*;
+use bevy::{
+    ecs::entity::{EntityMapper, MapEntities},
+    prelude::*,
+};
 use core::fmt::Display;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    base::Base,
+    config::{FlockingConfig, GameConfig},
     player::{ForPlayer, Player},
-    werewolf::Werewolf,
+    spawning::{
+        spawn_corral_walls, spawn_free_corrals, spawn_player_base, spawn_player_corral,
+        spawn_werewolf_with_base_and_corrals,
+    },
+    state::{InRound, SpawnSet},
 };
 
+pub struct CorralPlugin;
+
+impl Plugin for CorralPlugin {
+    fn build(&self, app: &mut App) {
+        // the corrals of the werewolves are spawned with them in the werewolf plugin
+        app.add_systems(
+            OnEnter(InRound),
+            (
+                spawn_player_corral.after(spawn_player_base),
+                spawn_free_corrals.after(spawn_werewolf_with_base_and_corrals),
+            )
+                .in_set(SpawnSet::Entities),
+        );
+        // the player is spawned in the player plugin, so it can be assigned only after all the
+        // spawning is done
+        app.add_systems(
+            OnEnter(InRound),
+            assign_player_to_corral.in_set(SpawnSet::Assign),
+        );
+        app.add_systems(
+            OnEnter(InRound),
+            spawn_corral_walls.in_set(SpawnSet::Decorate),
+        );
+    }
+}
+
 // this will be the upper left corenr of the corral
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct ChickenCorral {
     pub belongs_to: Option<Entity>,
     pub length: usize,
     pub heigth: usize,
+    /// overrides the flocking from the config
+    #[serde(default)]
+    pub flocking: Option<FlockingConfig>,
+}
+
+impl ChickenCorral {
+    /// The area inside of the walls, the pos is the center of the corral
+    pub fn rect(&self, pos: Vec2, config: &GameConfig) -> Rect {
+        let size = Vec2::new(self.length as f32, self.heigth as f32) * config.corral.wall_length;
+        Rect::from_center_size(pos, size)
+    }
+}
+
+impl MapEntities for ChickenCorral {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.belongs_to = self.belongs_to.map(|owner| entity_mapper.map_entity(owner));
+    }
 }
 
 #[derive(Copy, Clone)]
 pub enum WallType {
     Corner,
     Edge,
+    /// only the player can go through it
+    Gate,
 }
 
 impl Display for WallType {
@@ -26,6 +79,7 @@ impl Display for WallType {
         match self {
             WallType::Corner => write!(f, "C"),
             WallType::Edge => write!(f, "E"),
+            WallType::Gate => write!(f, "G"),
         }
     }
 }
@@ -37,7 +91,7 @@ pub struct ChickenCorralWall {
 
 pub fn assign_player_to_corral(
     mut player_q: Query<(&mut Player, Entity)>,
-    mut corral_q: Query<(&mut ChickenCorral, Entity)>,
+    mut corral_q: Query<(&mut ChickenCorral, Entity), With<ForPlayer>>,
 ) {
     let (mut player, player_ent) = player_q.get_single_mut().unwrap();
     for (mut corral, co_ent) in corral_q.iter_mut() {
@@ -49,28 +103,3 @@ pub fn assign_player_to_corral(
         }
     }
 }
-
-pub fn assign_werewolf_to_corral(
-    mut werewolf_q: Query<
-        (&mut Werewolf, Entity, &Transform),
-        (Without<Base>, Without<ChickenCorral>),
-    >,
-    mut corral_q: Query<
-        (&mut ChickenCorral, Entity, &Transform),
-        (Without<ForPlayer>, Without<Werewolf>),
-    >,
-) {
-    for (mut werewolf, w_ent, w_pos) in werewolf_q.iter_mut() {
-        let mut nearest_corral = (Entity::from_raw(0), 10000000.0);
-        for (_, co_ent, co_pos) in corral_q.iter_mut() {
-            if w_pos.translation.distance(co_pos.translation) < nearest_corral.1 {
-                nearest_corral = (co_ent, co_pos.translation.distance(w_pos.translation));
-            }
-        }
-
-        let (mut corral, co_ent, co_pos) = corral_q.get_mut(nearest_corral.0).unwrap();
-        corral.belongs_to = Some(w_ent);
-        werewolf.corral = Some(co_ent);
-        werewolf.corral_pos = Some(co_pos.translation.xy());
-    }
-}
@@ -0,0 +1,282 @@
+use bevy::prelude::*;
+
+use crate::{
+    base::Base,
+    chicken::Chicken,
+    chicken_corral::{ChickenCorralWall, WallType},
+    config::{config_reloaded, GameConfig},
+    flocking::flock_chickens,
+    map::{keep_in_arena, Obstacle},
+    player::{move_player, respawn_player, Player},
+    state::{GameState, InRound},
+    werewolf::{werewolf_behave, Werewolf},
+};
+
+/// The moving entities are pushed out of the walls, obstacles and bases.
+///
+/// Every collider is a circle or a rectangle, the movers (player, werewolves and chickens) are
+/// always circles. The movers are put on the edge of the thing they walked into, so they are
+/// sliding along it. The layers are saying, what is colliding with what (see `collides`).
+pub struct CollisionPlugin;
+
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>();
+
+        app.add_systems(Update, add_colliders.run_if(in_state(InRound)));
+        app.add_systems(
+            Update,
+            // everything is pushed out after it was moved in this frame
+            resolve_collisions
+                .after(move_player)
+                .after(respawn_player)
+                .after(werewolf_behave)
+                .after(flock_chickens)
+                .before(keep_in_arena)
+                .run_if(in_state(GameState::Playing)),
+        );
+        app.add_systems(Update, apply_config_to_colliders.run_if(config_reloaded));
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CollisionLayer {
+    Player,
+    Werewolf,
+    Chicken,
+    /// the walls of the corrals
+    Fence,
+    /// the gate in the wall of the corral, it is a fence for everyone except the player
+    Gate,
+    /// only the trees and the rocks, the bushes are not blocking
+    Obstacle,
+    Base,
+}
+
+impl CollisionLayer {
+    /// These are never pushed, only the others are pushed out of them
+    pub fn is_static(&self) -> bool {
+        matches!(
+            self,
+            CollisionLayer::Fence
+                | CollisionLayer::Gate
+                | CollisionLayer::Obstacle
+                | CollisionLayer::Base
+        )
+    }
+
+    /// The angry chickens are jumping over these
+    pub fn is_fence(&self) -> bool {
+        matches!(self, CollisionLayer::Fence | CollisionLayer::Gate)
+    }
+}
+
+/// Everything is colliding with everything, except:
+/// - the chickens between each other, they are kept apart by their flock (see flocking.rs)
+/// - the player and the gates, the player is opening the gates of the corrals
+/// - the static things between each other, they are never moving
+pub fn collides(a: CollisionLayer, b: CollisionLayer) -> bool {
+    use CollisionLayer::*;
+
+    match (a, b) {
+        (Chicken, Chicken) => false,
+        (Player, Gate) | (Gate, Player) => false,
+        (a, b) => !(a.is_static() && b.is_static()),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Shape {
+    Circle { radius: f32 },
+    Rect { half_size: Vec2 },
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Collider {
+    pub shape: Shape,
+    pub layer: CollisionLayer,
+}
+
+impl Collider {
+    pub fn circle(radius: f32, layer: CollisionLayer) -> Self {
+        Self {
+            shape: Shape::Circle { radius },
+            layer,
+        }
+    }
+
+    pub fn rect(size: Vec2, layer: CollisionLayer) -> Self {
+        Self {
+            shape: Shape::Rect {
+                half_size: size / 2.,
+            },
+            layer,
+        }
+    }
+}
+
+/// How far the circle must be moved to get out of the other collider
+fn penetration(pos: Vec2, radius: f32, other_pos: Vec2, other: Shape) -> Option<Vec2> {
+    match other {
+        Shape::Circle {
+            radius: other_radius,
+        } => {
+            let offset = pos - other_pos;
+            let min_distance = radius + other_radius;
+            (offset.length() < min_distance).then(|| {
+                offset.try_normalize().unwrap_or(Vec2::Y) * (min_distance - offset.length())
+            })
+        }
+        Shape::Rect { half_size } => {
+            let offset = pos - other_pos;
+            let closest = offset.clamp(-half_size, half_size);
+
+            if closest != offset {
+                let out = offset - closest;
+                (out.length() < radius).then(|| out.normalize() * (radius - out.length()))
+            } else {
+                // the center is in the rectangle, so it is pushed out to the nearest side
+                let to_side = half_size - offset.abs();
+                if to_side.x < to_side.y {
+                    Some(Vec2::X * offset.x.signum() * (to_side.x + radius))
+                } else {
+                    Some(Vec2::Y * offset.y.signum() * (to_side.y + radius))
+                }
+            }
+        }
+    }
+}
+
+/// Adds the colliders to the new entities, also to the ones from the loaded game
+#[allow(clippy::type_complexity)]
+pub fn add_colliders(
+    mut commands: Commands,
+    movers_q: Query<
+        (Entity, Has<Player>, Has<Werewolf>),
+        Or<(Added<Player>, Added<Werewolf>, Added<Chicken>)>,
+    >,
+    walls_q: Query<(Entity, &Sprite, &ChickenCorralWall), Added<ChickenCorralWall>>,
+    obstacles_q: Query<(Entity, &Obstacle), Added<Obstacle>>,
+    bases_q: Query<Entity, Added<Base>>,
+    config: Res<GameConfig>,
+) {
+    for (mover_ent, is_player, is_werewolf) in movers_q.iter() {
+        commands
+            .entity(mover_ent)
+            .insert(mover_collider(is_player, is_werewolf, &config));
+    }
+
+    for (wall_ent, sprite, wall) in walls_q.iter() {
+        let size = sprite.custom_size.unwrap_or_default();
+        let layer = match wall.wall_type {
+            WallType::Gate => CollisionLayer::Gate,
+            WallType::Corner | WallType::Edge => CollisionLayer::Fence,
+        };
+        commands
+            .entity(wall_ent)
+            .insert(Collider::rect(size, layer));
+    }
+
+    for (obstacle_ent, obstacle) in obstacles_q.iter() {
+        if obstacle.kind.is_blocking() {
+            commands
+                .entity(obstacle_ent)
+                .insert(Collider::circle(obstacle.radius, CollisionLayer::Obstacle));
+        }
+    }
+
+    for base_ent in bases_q.iter() {
+        commands.entity(base_ent).insert(Collider::rect(
+            Vec2::splat(config.base.size),
+            CollisionLayer::Base,
+        ));
+    }
+}
+
+fn mover_collider(is_player: bool, is_werewolf: bool, config: &GameConfig) -> Collider {
+    if is_player {
+        Collider::circle(config.player.size / 2., CollisionLayer::Player)
+    } else if is_werewolf {
+        Collider::circle(config.werewolf.size / 2., CollisionLayer::Werewolf)
+    } else {
+        Collider::circle(config.chicken.size / 2., CollisionLayer::Chicken)
+    }
+}
+
+pub fn resolve_collisions(
+    mut colliders_q: Query<(&mut Transform, &Collider, Option<&mut Chicken>)>,
+) {
+    // the positions are changed in here, so the mover pushed out of one wall is checked with the
+    // next one from its new position (this is the sliding in the corners)
+    let mut colliders: Vec<(Vec2, Collider, bool)> = colliders_q
+        .iter()
+        .map(|(pos, collider, chicken)| {
+            // the angry chicken is jumping over the walls of the corral
+            let jumping = chicken.is_some_and(|chicken| chicken.is_aggressive());
+            (pos.translation.xy(), *collider, jumping)
+        })
+        .collect();
+    let mut pushed_from = vec![Vec2::ZERO; colliders.len()];
+
+    for i in 0..colliders.len() {
+        let (_, collider, jumping) = colliders[i];
+        let Shape::Circle { radius } = collider.shape else {
+            continue;
+        };
+        if collider.layer.is_static() {
+            continue;
+        }
+
+        for j in 0..colliders.len() {
+            let (other_pos, other, _) = colliders[j];
+            if i == j
+                || !collides(collider.layer, other.layer)
+                || (jumping && other.layer.is_fence())
+            {
+                continue;
+            }
+            // the pair of the movers is pushed only once
+            if !other.layer.is_static() && j < i {
+                continue;
+            }
+
+            let Some(push) = penetration(colliders[i].0, radius, other_pos, other.shape) else {
+                continue;
+            };
+            if other.layer.is_static() {
+                colliders[i].0 += push;
+                pushed_from[i] += push;
+            } else {
+                // both movers are going away from each other
+                colliders[i].0 += push / 2.;
+                colliders[j].0 -= push / 2.;
+            }
+        }
+    }
+
+    for (i, (mut pos, _, chicken)) in colliders_q.iter_mut().enumerate() {
+        let new_pos = colliders[i].0;
+        if pos.translation.xy() != new_pos {
+            pos.translation = new_pos.extend(pos.translation.z);
+        }
+
+        // the chicken is turning away from the wall, so it is not walking into it forever
+        if let (Some(mut chicken), Some(normal)) = (chicken, pushed_from[i].try_normalize()) {
+            chicken.bounce_off(normal);
+        }
+    }
+}
+
+// the walls and the obstacles are not changed by the config
+pub fn apply_config_to_colliders(
+    mut colliders_q: Query<(&mut Collider, Has<Player>, Has<Werewolf>, Has<Base>)>,
+    config: Res<GameConfig>,
+) {
+    for (mut collider, is_player, is_werewolf, is_base) in colliders_q.iter_mut() {
+        if is_base {
+            *collider = Collider::rect(Vec2::splat(config.base.size), CollisionLayer::Base);
+        } else if !collider.layer.is_static() {
+            *collider = mover_collider(is_player, is_werewolf, &config);
+        }
+    }
+}
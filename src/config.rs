@@ -0,0 +1,855 @@
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{map::corral_footprint, settings::*};
+
+/// The config file, which is loaded on start if it exists
+pub const DEFAULT_CONFIG_PATH: &str = "config.ron";
+/// How often the config file is checked for the changes (in seconds)
+pub const CONFIG_CHECK_DELTA: f32 = 1.0;
+
+pub struct ConfigPlugin;
+
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>();
+
+        // the reloaded config must be applied in the same frame by the systems of the other
+        // plugins, which are running on `config_reloaded`
+        app.add_systems(PreUpdate, reload_config);
+    }
+}
+
+/// Is inserted by the app, when the config was loaded from a file, so the file can be reloaded
+/// while the game is running
+#[derive(Resource)]
+pub struct ConfigWatcher {
+    path: PathBuf,
+    last_modified: Option<SystemTime>,
+    check_timer: Timer,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let last_modified = modified_time(&path);
+
+        Self {
+            path,
+            last_modified,
+            check_timer: Timer::from_seconds(CONFIG_CHECK_DELTA, TimerMode::Repeating),
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// All the tuning of the game.
+///
+/// The default values are the constants from the settings.rs, so every field can be left out in
+/// the config file.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct GameConfig {
+    pub chicken_spawn_delta: f32,
+    pub text_z: f32,
+    /// the same seed gives the same map and the same decisions of the ai, see rng.rs
+    pub seed: Option<u64>,
+    pub round: RoundConfig,
+    pub camera: CameraConfig,
+    pub player: PlayerConfig,
+    pub werewolf: WerewolfConfig,
+    pub base: BaseConfig,
+    pub chicken: ChickenConfig,
+    pub corral: CorralConfig,
+    pub spawning: SpawningConfig,
+    pub map: MapConfig,
+    pub minimap: MinimapConfig,
+    pub fog: FogConfig,
+    pub save: SaveConfig,
+    pub editor: EditorConfig,
+}
+
+/// The objectives of the round, see round.rs
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RoundConfig {
+    /// the first one, who has this amount of chickens in the base, wins
+    pub target_chickens: Option<usize>,
+    /// the round length in seconds
+    pub time_limit: Option<f32>,
+    /// if the player base must have more chickens than every werewolf base after the time limit
+    pub outcollect_werewolves: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct CameraConfig {
+    pub max_speed: f32,
+    // max and min accelerations of camera with the different distance to player
+    pub max_distance_to_player: f32,
+    pub min_distance_to_player: f32,
+    pub zoom_out_key: KeyCode,
+    pub zoom_in_key: KeyCode,
+    pub zoom_speed: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct PlayerConfig {
+    pub speed: f32,
+    pub size: f32,
+    pub z: f32,
+    pub catching_radius: f32,
+    pub max_inventory_space: usize,
+    pub sight_distance: f32,
+    pub default_catching_radius_multiplier: f32,
+    pub default_sight_multiplier: f32,
+    pub default_speed_multiplier: f32,
+    pub lvlup_speed: f32,
+    pub lvlup_catching_radius: f32,
+    pub lvlup_sight: f32,
+    pub catching_radius_color: Color,
+    pub lvlup_speed_price: usize,
+    pub lvlup_catching_radius_price: usize,
+    pub lvlup_sight_price: usize,
+    pub max_health: f32,
+    /// how long the player is knocked out (in seconds)
+    pub knockout_delta: f32,
+    /// how long the key must be held in a werewolf base to take one chicken from there
+    pub loot_delta: f32,
+    pub key_up: KeyCode,
+    pub key_down: KeyCode,
+    pub key_left: KeyCode,
+    pub key_right: KeyCode,
+    pub key_catch: KeyCode,
+    pub key_give: KeyCode,
+    pub key_upgrade: KeyCode,
+    pub key_loot: KeyCode,
+    pub key_pause: KeyCode,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct WerewolfConfig {
+    pub color: Color,
+    pub speed: f32,
+    pub size: f32,
+    pub z: f32,
+    pub catching_radius: f32,
+    pub catching_try_speed: f32,
+    pub behaviour_change_delta: f32,
+    /// how near to the fence of its corral the werewolf is going
+    pub min_distance_to_corral: f32,
+    pub max_inventory_space: usize,
+    pub catching_radius_color: Color,
+    /// the werewolf is hunting the player in this distance
+    pub sight_distance: f32,
+    pub hunt_speed: f32,
+    pub attack_radius: f32,
+    pub attack_damage: f32,
+    /// the time between the attacks (in seconds)
+    pub attack_delta: f32,
+    /// how often the werewolf with the empty inventory is thinking about the raid of the player
+    pub raid_try_delta: f32,
+    /// how probably it goes to steal the chickens from the player's base (or corral)
+    pub raid_chance: f64,
+    /// the size of the cells in the grid, on which the werewolves are finding their way
+    pub nav_cell_size: f32,
+    /// how long the werewolf must be stuck, before it is looking for another way (in seconds)
+    pub replan_delay: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct BaseConfig {
+    pub catching_radius: f32,
+    pub size: f32,
+    pub color: Color,
+    pub z: f32,
+    pub catching_radius_color: Color,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ChickenConfig {
+    pub calm_speed: f32,
+    pub mad_speed: f32,
+    pub size: f32,
+    pub behaviour_change_delta: f32,
+    pub color: Color,
+    pub z: f32,
+    /// how probably the chicken is fighting back, when the player is trying to catch it
+    pub aggressive_chance: f64,
+    pub aggressive_speed: f32,
+    pub aggressive_color: Color,
+    /// the aggressive chicken is calming down, when the player is further
+    pub aggressive_give_up_distance: f32,
+    /// how many times the aggressive chicken must be catched
+    pub struggle_presses: usize,
+    pub peck_distance: f32,
+    pub peck_delta: f32,
+    /// the speed multiplier of the pecked player
+    pub peck_slowdown: f32,
+    /// how long the player is slower after the peck
+    pub peck_slow_delta: f32,
+    /// the chicken is running away from the player and the werewolves in this distance
+    pub flee_radius: f32,
+    pub panic_speed: f32,
+    /// how long the chicken is panicking after it lost the predator from the sight
+    pub panic_delta: f32,
+    /// after the panic the chicken is too tired to run away for a while
+    pub panic_cooldown: f32,
+    /// the default steering of the flocks, every corral can have its own (see CorralPlace)
+    pub flocking: FlockingConfig,
+}
+
+/// The steering of the chickens in the same corral, see flocking.rs
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct FlockingConfig {
+    /// the chickens closer than this are in the same group
+    pub neighbour_radius: f32,
+    /// the chickens closer than this are pushing each other away
+    pub separation_distance: f32,
+    /// the speed of pushing away
+    pub separation: f32,
+    /// how much the chicken is taking over the velocity of its neighbours
+    pub alignment: f32,
+    /// how fast the chicken is going to the center of its neighbours (per second)
+    pub cohesion: f32,
+    pub max_speed: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct CorralConfig {
+    pub default_length: usize,
+    pub default_heigth: usize,
+    pub z: f32,
+    pub wall_heigth: f32,
+    // legth must be langet than heigth
+    pub wall_length: f32,
+    pub wall_color: Color,
+    /// the gate in the bottom wall, the player is going through it into the corral
+    pub gate_color: Color,
+}
+
+/// See the spawning.rs for the description of the spawning rings
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct SpawningConfig {
+    pub p_corral_distance_from_center: f32,
+    // werewolf distance from center must be bigger as from werewolf!!!
+    pub w_corral_distance_from_center: f32,
+    pub base_distance_from_entity: f32,
+    pub werewolf_distance_to_center: f32,
+    pub werewolf_amount: usize,
+    // preventing spawning the entities in the simillar parts of the circle
+    pub angle_margin: f32,
+}
+
+/// The generated arena with its ground and obstacles, see map.rs
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct MapConfig {
+    /// the hand made map (see map_file.rs), without it the map is generated
+    pub file: Option<String>,
+    /// the side length of the square arena, its center is in (0, 0)
+    pub size: f32,
+    pub tile_size: f32,
+    pub ground_z: f32,
+    /// how many areas with the different biomes are on the ground
+    pub biome_amount: usize,
+    pub grass: BiomeConfig,
+    pub mud: BiomeConfig,
+    pub forest: BiomeConfig,
+    pub obstacle_z: f32,
+    /// the free space between the obstacles and the other things
+    pub clearance: f32,
+    /// the werewolf in the bush is seen, when the player is nearer than this
+    pub bush_reveal_distance: f32,
+    pub trees: ObstacleConfig,
+    pub rocks: ObstacleConfig,
+    pub bushes: ObstacleConfig,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BiomeConfig {
+    pub color: Color,
+    /// the speed of everything, what is moving on this biome, is multiplied with it
+    pub speed_multiplier: f32,
+    /// how often the biome is chosen compared to the other ones
+    pub weight: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ObstacleConfig {
+    pub amount: usize,
+    pub radius: f32,
+    pub color: Color,
+}
+
+/// The overview of the arena in the corner, see minimap.rs
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct MinimapConfig {
+    /// if the minimap is there on the start of the round
+    pub shown: bool,
+    pub key_toggle: KeyCode,
+    /// the side length in pixels, the whole arena is scaled to it
+    pub size: f32,
+    /// from the corner of the window in pixels
+    pub margin: f32,
+    pub background_color: Color,
+    /// the icons and the text are in the world units, so they are scaled down with the arena
+    pub icon_size: f32,
+    pub text_size: f32,
+    /// the player with its base and corral
+    pub player_color: Color,
+}
+
+/// The fog of war outside of the sight of the player, see fog.rs
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct FogConfig {
+    /// over everything else in the world
+    pub z: f32,
+    /// of the places, which were never seen
+    pub color: Color,
+    /// the explored places are dimmed with this part of the alpha of the color
+    pub explored_alpha: f32,
+}
+
+/// Where the game is saved, see save.rs
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct SaveConfig {
+    pub path: String,
+    pub key_save: KeyCode,
+    pub key_load: KeyCode,
+}
+
+/// The level editor, see editor.rs
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct EditorConfig {
+    /// opens and closes the editor from the main menu
+    pub key_toggle: KeyCode,
+    pub camera_speed: f32,
+    /// where the map is saved, when no map file is set in the map section
+    pub default_path: String,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Invalid(String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "can't read the config file: {}", err),
+            ConfigError::Parse(err) => write!(f, "can't parse the config file: {}", err),
+            ConfigError::Invalid(reason) => write!(f, "invalid config: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(err: std::io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for ConfigError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        ConfigError::Parse(err)
+    }
+}
+
+impl GameConfig {
+    /// Reads the config from the RON file and checks it
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let config: GameConfig = ron::from_str(&fs::read_to_string(path)?)?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Checks the things, which would break the spawning or the behaviour of the entities
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.corral.wall_length <= self.corral.wall_heigth {
+            return Err(ConfigError::Invalid(format!(
+                "corral wall_length ({}) must be larger than wall_heigth ({})",
+                self.corral.wall_length, self.corral.wall_heigth
+            )));
+        }
+
+        if !(0.0..=1.0).contains(&self.chicken.aggressive_chance) {
+            return Err(ConfigError::Invalid(format!(
+                "chicken aggressive_chance ({}) must be between 0 and 1",
+                self.chicken.aggressive_chance
+            )));
+        }
+
+        if !(0.0..=1.0).contains(&self.werewolf.raid_chance) {
+            return Err(ConfigError::Invalid(format!(
+                "werewolf raid_chance ({}) must be between 0 and 1",
+                self.werewolf.raid_chance
+            )));
+        }
+
+        if self.werewolf.nav_cell_size <= 0. {
+            return Err(ConfigError::Invalid(format!(
+                "werewolf nav_cell_size ({}) must be positive",
+                self.werewolf.nav_cell_size
+            )));
+        }
+
+        // the chickens are spawned at least one wall away from the walls of the corral
+        if self.corral.default_length.min(self.corral.default_heigth) <= 2 {
+            return Err(ConfigError::Invalid(format!(
+                "corral default_length ({}) and default_heigth ({}) must be bigger than 2",
+                self.corral.default_length, self.corral.default_heigth
+            )));
+        }
+
+        if self.spawning.w_corral_distance_from_center <= self.spawning.werewolf_distance_to_center
+        {
+            return Err(ConfigError::Invalid(format!(
+                "w_corral_distance_from_center ({}) must be bigger than werewolf_distance_to_center ({})",
+                self.spawning.w_corral_distance_from_center,
+                self.spawning.werewolf_distance_to_center
+            )));
+        }
+
+        // the bases are compared at the end of the round, so there must be an end
+        if self.round.outcollect_werewolves && self.round.time_limit.is_none() {
+            return Err(ConfigError::Invalid(
+                "round outcollect_werewolves needs a time_limit".to_string(),
+            ));
+        }
+
+        if self.spawning.werewolf_amount == 0 {
+            return Err(ConfigError::Invalid(
+                "werewolf_amount must be at least 1".to_string(),
+            ));
+        }
+
+        // every werewolf gets its own part of the circle without the margins, the last part is
+        // the smallest one
+        let angle_step = 360. / self.spawning.werewolf_amount as f32;
+        if self.spawning.angle_margin * (2 * self.spawning.werewolf_amount - 1) as f32 / 2.
+            >= angle_step
+        {
+            return Err(ConfigError::Invalid(format!(
+                "angle_margin ({}) is too big for {} werewolves",
+                self.spawning.angle_margin, self.spawning.werewolf_amount
+            )));
+        }
+
+        if self.map.tile_size <= 0. {
+            return Err(ConfigError::Invalid(format!(
+                "map tile_size ({}) must be bigger than 0",
+                self.map.tile_size
+            )));
+        }
+
+        // the corrals with their walls must fit in the arena, even before the rings are getting
+        // bigger because of the other things
+        let corral_radius =
+            corral_footprint(self, self.corral.default_length, self.corral.default_heigth);
+        let corral_distance = self
+            .spawning
+            .w_corral_distance_from_center
+            .max(self.spawning.p_corral_distance_from_center);
+        if corral_distance + corral_radius >= self.map.size / 2. {
+            return Err(ConfigError::Invalid(format!(
+                "map size ({}) is too small for the corrals {} from the center",
+                self.map.size, corral_distance
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+pub fn reload_config(
+    watcher: Option<ResMut<ConfigWatcher>>,
+    mut config: ResMut<GameConfig>,
+    time: Res<Time>,
+) {
+    let Some(mut watcher) = watcher else {
+        return;
+    };
+    if !watcher.check_timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let modified = modified_time(&watcher.path);
+    if modified == watcher.last_modified {
+        return;
+    }
+    watcher.last_modified = modified;
+
+    // if the new config is broken, the game is going on with the old one
+    match GameConfig::load(&watcher.path) {
+        Ok(new_config) => {
+            info!("config reloaded from {}", watcher.path.display());
+            *config = new_config;
+        }
+        Err(err) => error!("{}: {}", watcher.path.display(), err),
+    }
+}
+
+/// Run condition for the systems, which are applying the reloaded config to the already spawned
+/// entities (the config, which was there from the start, is not counted)
+pub fn config_reloaded(config: Res<GameConfig>) -> bool {
+    config.is_changed() && !config.is_added()
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            chicken_spawn_delta: CHICKEN_SPAWN_DELTA,
+            text_z: TEXT_Z,
+            seed: SEED,
+            round: RoundConfig::default(),
+            camera: CameraConfig::default(),
+            player: PlayerConfig::default(),
+            werewolf: WerewolfConfig::default(),
+            base: BaseConfig::default(),
+            chicken: ChickenConfig::default(),
+            corral: CorralConfig::default(),
+            spawning: SpawningConfig::default(),
+            map: MapConfig::default(),
+            minimap: MinimapConfig::default(),
+            fog: FogConfig::default(),
+            save: SaveConfig::default(),
+            editor: EditorConfig::default(),
+        }
+    }
+}
+
+impl Default for RoundConfig {
+    fn default() -> Self {
+        Self {
+            target_chickens: ROUND_TARGET_CHICKENS,
+            time_limit: ROUND_TIME_LIMIT,
+            outcollect_werewolves: ROUND_OUTCOLLECT_WEREWOLVES,
+        }
+    }
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self {
+            max_speed: MAX_CAMERA_SPEED,
+            max_distance_to_player: MAX_CAMERA_DISTANCE_TO_PLAYER,
+            min_distance_to_player: MIN_CAMERA_DISTANCE_TO_PLAYER,
+            zoom_out_key: ZOOM_OUT_KEY,
+            zoom_in_key: ZOOM_IN_KEY,
+            zoom_speed: ZOOM_SPEED,
+        }
+    }
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        Self {
+            speed: PLAYER_SPEED,
+            size: PLAYER_SIZE,
+            z: PLAYER_Z,
+            catching_radius: PLAYER_CATCHING_RADIUS,
+            max_inventory_space: PLAYER_MAX_INVENTORY_SPACE,
+            sight_distance: PLAYER_SIGHT_DISTANCE,
+            default_catching_radius_multiplier: PLAYER_DEFAULT_CATCHING_RADIUS_MULTIPLIER,
+            default_sight_multiplier: PLAYER_DEFAULT_SIGHT_MULTIPLIER,
+            default_speed_multiplier: PLAYER_DEFAULT_SPEED_MULTIPLIER,
+            lvlup_speed: PLAYER_LVLUP_SPEED,
+            lvlup_catching_radius: PLAYER_LVLUP_CATCHING_RADIUS,
+            lvlup_sight: PLAYER_LVLUP_SIGHT,
+            catching_radius_color: BASE_PLAYER_CATCHING_RADIUS_COLOR,
+            lvlup_speed_price: PLAYER_LVLUP_SPEED_PRICE,
+            lvlup_catching_radius_price: PLAYER_LVLUP_CATCHING_RADIUS_PRICE,
+            lvlup_sight_price: PLAYER_LVLUP_SIGHT_PRICE,
+            max_health: PLAYER_MAX_HEALTH,
+            knockout_delta: PLAYER_KNOCKOUT_DELTA,
+            loot_delta: PLAYER_LOOT_DELTA,
+            key_up: PLAYER_KEY_UP,
+            key_down: PLAYER_KEY_DOWN,
+            key_left: PLAYER_KEY_LEFT,
+            key_right: PLAYER_KEY_RIGHT,
+            key_catch: PLAYER_KEY_CATCH,
+            key_give: PLAYER_KEY_GIVE,
+            key_upgrade: PLAYER_KEY_UPGRADE,
+            key_loot: PLAYER_KEY_LOOT,
+            key_pause: PLAYER_KEY_PAUSE,
+        }
+    }
+}
+
+impl Default for WerewolfConfig {
+    fn default() -> Self {
+        Self {
+            color: WEREWOLF_COLOR,
+            speed: WEREWOLF_SPEED,
+            size: WEREWOLF_SIZE,
+            z: WEREWOLF_Z,
+            catching_radius: WEREWOLF_CATCHING_RADIUS,
+            catching_try_speed: WEREWOLF_CATCHING_TRY_SPEED,
+            behaviour_change_delta: WEREWOLF_BEHAVIOUR_CHANGE_DELTA,
+            min_distance_to_corral: WEREWOLF_MIN_DISTANCE_TO_CORRAL,
+            max_inventory_space: WEREWOLF_MAX_INVENTORY_SPACE,
+            catching_radius_color: WEREWOLF_CATCHING_RADIUS_COLOR,
+            sight_distance: WEREWOLF_SIGHT_DISTANCE,
+            hunt_speed: WEREWOLF_HUNT_SPEED,
+            attack_radius: WEREWOLF_ATTACK_RADIUS,
+            attack_damage: WEREWOLF_ATTACK_DAMAGE,
+            attack_delta: WEREWOLF_ATTACK_DELTA,
+            raid_try_delta: WEREWOLF_RAID_TRY_DELTA,
+            raid_chance: WEREWOLF_RAID_CHANCE,
+            nav_cell_size: WEREWOLF_NAV_CELL_SIZE,
+            replan_delay: WEREWOLF_REPLAN_DELAY,
+        }
+    }
+}
+
+impl Default for BaseConfig {
+    fn default() -> Self {
+        Self {
+            catching_radius: BASE_CATCHING_RADIUS,
+            size: BASE_SIZE,
+            color: BASE_COLOR,
+            z: BASE_Z,
+            catching_radius_color: BASE_CATCHING_RADIUS_COLOR,
+        }
+    }
+}
+
+impl Default for ChickenConfig {
+    fn default() -> Self {
+        Self {
+            calm_speed: CHICKEN_CALM_SPEED,
+            mad_speed: CHICKEN_MAD_SPEED,
+            size: CHICKEN_SIZE,
+            behaviour_change_delta: CHICKEN_BEHAVIOUR_CHANGE_DELTA,
+            color: CHICKEN_COLOR,
+            z: CHICKEN_Z,
+            aggressive_chance: CHICKEN_AGGRESSIVE_CHANCE,
+            aggressive_speed: CHICKEN_AGGRESSIVE_SPEED,
+            aggressive_color: CHICKEN_AGGRESSIVE_COLOR,
+            aggressive_give_up_distance: CHICKEN_AGGRESSIVE_GIVE_UP_DISTANCE,
+            struggle_presses: CHICKEN_STRUGGLE_PRESSES,
+            peck_distance: CHICKEN_PECK_DISTANCE,
+            peck_delta: CHICKEN_PECK_DELTA,
+            peck_slowdown: CHICKEN_PECK_SLOWDOWN,
+            peck_slow_delta: CHICKEN_PECK_SLOW_DELTA,
+            flee_radius: CHICKEN_FLEE_RADIUS,
+            panic_speed: CHICKEN_PANIC_SPEED,
+            panic_delta: CHICKEN_PANIC_DELTA,
+            panic_cooldown: CHICKEN_PANIC_COOLDOWN,
+            flocking: FlockingConfig::default(),
+        }
+    }
+}
+
+impl Default for FlockingConfig {
+    fn default() -> Self {
+        Self {
+            neighbour_radius: FLOCKING_NEIGHBOUR_RADIUS,
+            separation_distance: FLOCKING_SEPARATION_DISTANCE,
+            separation: FLOCKING_SEPARATION,
+            alignment: FLOCKING_ALIGNMENT,
+            cohesion: FLOCKING_COHESION,
+            max_speed: FLOCKING_MAX_SPEED,
+        }
+    }
+}
+
+impl Default for CorralConfig {
+    fn default() -> Self {
+        Self {
+            default_length: DEFAULT_CORRAL_LENGTH,
+            default_heigth: DEFAULT_CORRAL_HEIGTH,
+            z: CORRAL_Z,
+            wall_heigth: CORRAL_WALL_HEIGTH,
+            wall_length: CORRAL_WALL_LENGTH,
+            wall_color: CORRAL_WALL_COLOR,
+            gate_color: CORRAL_GATE_COLOR,
+        }
+    }
+}
+
+impl Default for SpawningConfig {
+    fn default() -> Self {
+        Self {
+            p_corral_distance_from_center: P_CORRAL_DISTANCE_FROM_CENTER,
+            w_corral_distance_from_center: W_CORRAL_DISTANCE_FROM_CENTER,
+            base_distance_from_entity: BASE_DISTANCE_FROM_ENTITY,
+            werewolf_distance_to_center: WEREWOLF_DISTANCE_TO_CENTER,
+            werewolf_amount: WEREWOLF_AMOUNT,
+            angle_margin: ANGLE_MARGIN,
+        }
+    }
+}
+
+impl Default for MapConfig {
+    fn default() -> Self {
+        Self {
+            file: MAP_FILE.map(String::from),
+            size: MAP_SIZE,
+            tile_size: MAP_TILE_SIZE,
+            ground_z: MAP_GROUND_Z,
+            biome_amount: MAP_BIOME_AMOUNT,
+            grass: BiomeConfig {
+                color: GRASS_COLOR,
+                speed_multiplier: GRASS_SPEED_MULTIPLIER,
+                weight: GRASS_WEIGHT,
+            },
+            mud: BiomeConfig {
+                color: MUD_COLOR,
+                speed_multiplier: MUD_SPEED_MULTIPLIER,
+                weight: MUD_WEIGHT,
+            },
+            forest: BiomeConfig {
+                color: FOREST_COLOR,
+                speed_multiplier: FOREST_SPEED_MULTIPLIER,
+                weight: FOREST_WEIGHT,
+            },
+            obstacle_z: MAP_OBSTACLE_Z,
+            clearance: MAP_CLEARANCE,
+            bush_reveal_distance: MAP_BUSH_REVEAL_DISTANCE,
+            trees: ObstacleConfig {
+                amount: MAP_TREE_AMOUNT,
+                radius: MAP_TREE_RADIUS,
+                color: MAP_TREE_COLOR,
+            },
+            rocks: ObstacleConfig {
+                amount: MAP_ROCK_AMOUNT,
+                radius: MAP_ROCK_RADIUS,
+                color: MAP_ROCK_COLOR,
+            },
+            bushes: ObstacleConfig {
+                amount: MAP_BUSH_AMOUNT,
+                radius: MAP_BUSH_RADIUS,
+                color: MAP_BUSH_COLOR,
+            },
+        }
+    }
+}
+
+impl Default for MinimapConfig {
+    fn default() -> Self {
+        Self {
+            shown: MINIMAP_SHOWN,
+            key_toggle: MINIMAP_KEY_TOGGLE,
+            size: MINIMAP_SIZE,
+            margin: MINIMAP_MARGIN,
+            background_color: MINIMAP_BACKGROUND_COLOR,
+            icon_size: MINIMAP_ICON_SIZE,
+            text_size: MINIMAP_TEXT_SIZE,
+            player_color: MINIMAP_PLAYER_COLOR,
+        }
+    }
+}
+
+impl Default for FogConfig {
+    fn default() -> Self {
+        Self {
+            z: FOG_Z,
+            color: FOG_COLOR,
+            explored_alpha: FOG_EXPLORED_ALPHA,
+        }
+    }
+}
+
+impl Default for SaveConfig {
+    fn default() -> Self {
+        Self {
+            path: SAVE_PATH.to_string(),
+            key_save: SAVE_KEY,
+            key_load: LOAD_KEY,
+        }
+    }
+}
+
+impl Default for EditorConfig {
+    fn default() -> Self {
+        Self {
+            key_toggle: EDITOR_KEY_TOGGLE,
+            camera_speed: EDITOR_CAMERA_SPEED,
+            default_path: EDITOR_DEFAULT_PATH.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the text of the error is not checked, only that it is there
+    fn assert_invalid(config: GameConfig) {
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn default_config_is_valid() {
+        GameConfig::default().validate().unwrap();
+    }
+
+    #[test]
+    fn config_file_is_valid() {
+        GameConfig::load("config.ron").unwrap();
+    }
+
+    #[test]
+    fn wall_must_be_longer_than_high() {
+        let mut config = GameConfig::default();
+        config.corral.wall_heigth = config.corral.wall_length;
+        assert_invalid(config);
+    }
+
+    #[test]
+    fn outcollect_needs_time_limit() {
+        let mut config = GameConfig::default();
+        config.round.outcollect_werewolves = true;
+        config.round.time_limit = None;
+        assert_invalid(config);
+    }
+
+    #[test]
+    fn there_must_be_a_werewolf() {
+        let mut config = GameConfig::default();
+        config.spawning.werewolf_amount = 0;
+        assert_invalid(config);
+    }
+
+    #[test]
+    fn corral_must_be_bigger_than_two_walls() {
+        let mut config = GameConfig::default();
+        config.corral.default_heigth = 2;
+        assert_invalid(config);
+    }
+
+    #[test]
+    fn corrals_must_fit_in_the_arena() {
+        let mut config = GameConfig::default();
+        config.map.size = config.spawning.w_corral_distance_from_center * 2.;
+        assert_invalid(config);
+    }
+}
@@ -0,0 +1,623 @@
+use bevy::{color::palettes::css, prelude::*, window::PrimaryWindow};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    camera::{reset_camera, MainCamera},
+    config::GameConfig,
+    map::{
+        Biome, CorralOwner, CorralPlace, MapLayout, ObstacleKind, PendingMapFile, WerewolfPlaces,
+    },
+    map_file::{BiomeArea, MapFile, MapObstacle},
+    rng::GameRng,
+    state::GameState,
+};
+
+/// The level editor.
+///
+/// It is opened from the main menu (or with the toggle key) and edits the map file from the
+/// config, or the generated map, if there is no file. Everything is drawn with the gizmos and can
+/// be dragged with the mouse, the other things are changed in the side panel. The result is
+/// saved in the map file format (see map_file.rs), so it can be played right away. The played
+/// file is kept in the PendingMapFile, the config is not changed.
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>();
+        app.init_resource::<PendingMapFile>();
+
+        app.add_systems(OnEnter(GameState::Editor), (start_editor, reset_camera));
+        app.add_systems(OnExit(GameState::Editor), close_editor);
+        app.add_systems(
+            Update,
+            toggle_editor
+                .run_if(in_state(GameState::MainMenu).or_else(in_state(GameState::Editor))),
+        );
+        app.add_systems(
+            Update,
+            // the panel is first, so the mouse over it is not dragging the things behind it
+            (
+                editor_panel,
+                drag_editor_items,
+                move_editor_camera,
+                draw_editor_map,
+            )
+                .chain()
+                .run_if(in_state(GameState::Editor).and_then(resource_exists::<EditorMap>)),
+        );
+    }
+}
+
+/// The thing on the map, which can be selected in the editor
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EditorItem {
+    Player,
+    PlayerBase,
+    Werewolf(usize),
+    WerewolfBase(usize),
+    Corral(usize),
+    Obstacle(usize),
+    BiomeArea(usize),
+}
+
+/// The edited map, it is there only while the editor is open
+#[derive(Resource)]
+pub struct EditorMap {
+    pub map: MapFile,
+    pub path: String,
+    selected: Option<EditorItem>,
+    // the last cursor position, while something is dragged
+    drag_from: Option<Vec2>,
+    status: String,
+}
+
+impl EditorMap {
+    pub fn new(map: MapFile, path: String) -> Self {
+        Self {
+            map,
+            path,
+            selected: None,
+            drag_from: None,
+            status: String::new(),
+        }
+    }
+
+    // the later items are drawn over the earlier ones, so they are picked first
+    fn items(&self) -> Vec<EditorItem> {
+        let mut items: Vec<EditorItem> = (0..self.map.biome_areas.len())
+            .map(EditorItem::BiomeArea)
+            .collect();
+        items.extend((0..self.map.corrals.len()).map(EditorItem::Corral));
+        items.push(EditorItem::PlayerBase);
+        items.extend((0..self.map.werewolves.len()).map(EditorItem::WerewolfBase));
+        items.extend((0..self.map.obstacles.len()).map(EditorItem::Obstacle));
+        items.extend((0..self.map.werewolves.len()).map(EditorItem::Werewolf));
+        items.push(EditorItem::Player);
+        items
+    }
+
+    pub fn pos(&self, item: EditorItem) -> Vec2 {
+        match item {
+            EditorItem::Player => self.map.player,
+            EditorItem::PlayerBase => self.map.player_base,
+            EditorItem::Werewolf(i) => self.map.werewolves[i].werewolf,
+            EditorItem::WerewolfBase(i) => self.map.werewolves[i].base,
+            EditorItem::Corral(i) => self.map.corrals[i].pos,
+            EditorItem::Obstacle(i) => self.map.obstacles[i].pos,
+            EditorItem::BiomeArea(i) => {
+                let area = &self.map.biome_areas[i];
+                (area.min + area.max) / 2.
+            }
+        }
+    }
+
+    pub fn move_item(&mut self, item: EditorItem, delta: Vec2) {
+        match item {
+            EditorItem::Player => self.map.player += delta,
+            EditorItem::PlayerBase => self.map.player_base += delta,
+            EditorItem::Werewolf(i) => self.map.werewolves[i].werewolf += delta,
+            EditorItem::WerewolfBase(i) => self.map.werewolves[i].base += delta,
+            EditorItem::Corral(i) => self.map.corrals[i].pos += delta,
+            EditorItem::Obstacle(i) => self.map.obstacles[i].pos += delta,
+            EditorItem::BiomeArea(i) => {
+                let area = &mut self.map.biome_areas[i];
+                area.min += delta;
+                area.max += delta;
+            }
+        }
+    }
+
+    // the size of the item on the map as a rectangle
+    fn item_size(&self, item: EditorItem, config: &GameConfig) -> Vec2 {
+        match item {
+            EditorItem::Player => Vec2::splat(config.player.size),
+            EditorItem::PlayerBase | EditorItem::WerewolfBase(_) => Vec2::splat(config.base.size),
+            EditorItem::Werewolf(_) => Vec2::splat(config.werewolf.size),
+            EditorItem::Corral(i) => corral_size(&self.map.corrals[i], config),
+            EditorItem::Obstacle(i) => {
+                let obstacle = &self.map.obstacles[i];
+                Vec2::splat(obstacle_radius(obstacle, config) * 2.)
+            }
+            EditorItem::BiomeArea(i) => {
+                let area = &self.map.biome_areas[i];
+                (area.max - area.min).abs()
+            }
+        }
+    }
+
+    pub fn item_at(&self, pos: Vec2, config: &GameConfig) -> Option<EditorItem> {
+        self.items().into_iter().rev().find(|item| {
+            let offset = (pos - self.pos(*item)).abs();
+            let half_size = self.item_size(*item, config) / 2.;
+            offset.x <= half_size.x && offset.y <= half_size.y
+        })
+    }
+
+    /// Removes the item with everything, what belongs to it, the player and its base are staying
+    pub fn remove(&mut self, item: EditorItem) {
+        match item {
+            EditorItem::Player | EditorItem::PlayerBase => return,
+            EditorItem::Werewolf(i) | EditorItem::WerewolfBase(i) => {
+                self.map.werewolves.remove(i);
+                self.map
+                    .corrals
+                    .retain(|corral| corral.owner != CorralOwner::Werewolf(i));
+                // the werewolves after it are moved one place back
+                for corral in self.map.corrals.iter_mut() {
+                    if let CorralOwner::Werewolf(owner) = corral.owner {
+                        if owner > i {
+                            corral.owner = CorralOwner::Werewolf(owner - 1);
+                        }
+                    }
+                }
+            }
+            EditorItem::Corral(i) => {
+                self.map.corrals.remove(i);
+            }
+            EditorItem::Obstacle(i) => {
+                self.map.obstacles.remove(i);
+            }
+            EditorItem::BiomeArea(i) => {
+                self.map.biome_areas.remove(i);
+            }
+        }
+        self.selected = None;
+    }
+
+    /// Adds the werewolf with its base and corral around the position
+    pub fn add_werewolf(&mut self, pos: Vec2, config: &GameConfig) {
+        let index = self.map.werewolves.len();
+        self.map.werewolves.push(WerewolfPlaces {
+            werewolf: pos,
+            base: pos - Vec2::new(0., config.spawning.base_distance_from_entity),
+        });
+        self.map.corrals.push(CorralPlace {
+            pos: pos + Vec2::new(0., config.spawning.base_distance_from_entity * 3.),
+            length: config.corral.default_length,
+            heigth: config.corral.default_heigth,
+            owner: CorralOwner::Werewolf(index),
+            flocking: None,
+        });
+        self.selected = Some(EditorItem::Werewolf(index));
+    }
+}
+
+fn corral_size(corral: &CorralPlace, config: &GameConfig) -> Vec2 {
+    Vec2::new(corral.length as f32, corral.heigth as f32) * config.corral.wall_length
+}
+
+fn obstacle_radius(obstacle: &MapObstacle, config: &GameConfig) -> f32 {
+    obstacle
+        .radius
+        .unwrap_or(obstacle.kind.config(config).radius)
+}
+
+/// The last played map file, or the generated map, if there is no file yet
+pub fn start_editor(mut commands: Commands, pending: Res<PendingMapFile>, config: Res<GameConfig>) {
+    let path = pending
+        .path(&config)
+        .cloned()
+        .unwrap_or_else(|| config.editor.default_path.clone());
+
+    let map = match MapFile::load(&path, &config) {
+        Ok(map) => map,
+        Err(err) => {
+            info!("{}: {}, starting with the generated map", path, err);
+            let mut rng = GameRng::from_config(&config);
+            match MapLayout::generate(&config, &mut rng) {
+                Ok(layout) => MapFile::from_layout(&layout),
+                // the empty arena, the designer is placing everything
+                Err(err) => {
+                    error!("can't generate the map: {}", err);
+                    MapFile::from_layout(&MapLayout::with_ground(
+                        config.map.size,
+                        config.map.tile_size,
+                        |_| Biome::Grass,
+                    ))
+                }
+            }
+        }
+    };
+
+    commands.insert_resource(EditorMap::new(map, path));
+}
+
+pub fn close_editor(mut commands: Commands) {
+    commands.remove_resource::<EditorMap>();
+}
+
+pub fn toggle_editor(
+    input: Res<ButtonInput<KeyCode>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    config: Res<GameConfig>,
+) {
+    if !input.just_pressed(config.editor.key_toggle) {
+        return;
+    }
+
+    match state.get() {
+        GameState::MainMenu => next_state.set(GameState::Editor),
+        GameState::Editor => next_state.set(GameState::MainMenu),
+        _ => {}
+    }
+}
+
+pub fn editor_panel(
+    mut context: EguiContexts,
+    mut editor: ResMut<EditorMap>,
+    mut next_state: ResMut<NextState<GameState>>,
+    camera_q: Query<&Transform, With<MainCamera>>,
+    mut pending: ResMut<PendingMapFile>,
+    config: Res<GameConfig>,
+) {
+    // the new things are added in the middle of the screen
+    let view_center = camera_q
+        .get_single()
+        .map_or(Vec2::ZERO, |c_pos| c_pos.translation.xy());
+    let editor = &mut *editor;
+
+    egui::SidePanel::left("Map editor").show(context.ctx_mut(), |ui| {
+        ui.heading("Map editor");
+        ui.horizontal(|ui| {
+            ui.label("File:");
+            ui.text_edit_singleline(&mut editor.path);
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                editor.status = save_map(editor, &config);
+            }
+            if ui.button("Reload").clicked() {
+                match MapFile::load(&editor.path, &config) {
+                    Ok(map) => {
+                        *editor = EditorMap::new(map, editor.path.clone());
+                        editor.status = format!("loaded {}", editor.path);
+                    }
+                    Err(err) => editor.status = err.to_string(),
+                }
+            }
+            if ui.button("Play").clicked() {
+                editor.status = save_map(editor, &config);
+                if editor.map.validate(&config).is_ok() {
+                    pending.0 = Some(editor.path.clone());
+                    next_state.set(GameState::Playing);
+                }
+            }
+            if ui.button("Main menu").clicked() {
+                next_state.set(GameState::MainMenu);
+            }
+        });
+        ui.label(&editor.status);
+        if let Err(err) = editor.map.validate(&config) {
+            ui.colored_label(egui::Color32::RED, err.to_string());
+        }
+
+        ui.separator();
+        let mut size = editor.map.size(&config);
+        ui.horizontal(|ui| {
+            ui.label("Arena size:");
+            if ui
+                .add(egui::DragValue::new(&mut size).range(100.0..=20000.0))
+                .changed()
+            {
+                editor.map.size = Some(size);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Ground:");
+            biome_combo(ui, "ground", &mut editor.map.biome);
+        });
+        ui.checkbox(
+            &mut editor.map.random_obstacles,
+            "Random obstacles from the config",
+        );
+
+        ui.separator();
+        ui.horizontal_wrapped(|ui| {
+            if ui.button("+ Werewolf").clicked() {
+                editor.add_werewolf(view_center, &config);
+            }
+            if ui.button("+ Corral").clicked() {
+                editor.map.corrals.push(CorralPlace {
+                    pos: view_center,
+                    length: config.corral.default_length,
+                    heigth: config.corral.default_heigth,
+                    owner: CorralOwner::Nobody,
+                    flocking: None,
+                });
+                editor.selected = Some(EditorItem::Corral(editor.map.corrals.len() - 1));
+            }
+            for kind in [ObstacleKind::Tree, ObstacleKind::Rock, ObstacleKind::Bush] {
+                if ui.button(format!("+ {:?}", kind)).clicked() {
+                    editor.map.obstacles.push(MapObstacle {
+                        kind,
+                        pos: view_center,
+                        radius: None,
+                    });
+                    editor.selected = Some(EditorItem::Obstacle(editor.map.obstacles.len() - 1));
+                }
+            }
+            if ui.button("+ Biome area").clicked() {
+                let half_size = Vec2::splat(config.map.tile_size * 2.);
+                editor.map.biome_areas.push(BiomeArea {
+                    biome: Biome::Mud,
+                    min: view_center - half_size,
+                    max: view_center + half_size,
+                });
+                editor.selected = Some(EditorItem::BiomeArea(editor.map.biome_areas.len() - 1));
+            }
+        });
+
+        ui.separator();
+        let Some(item) = editor.selected else {
+            ui.label("Click on something to select it");
+            return;
+        };
+        ui.label(format!("{:?}", item));
+
+        let mut pos = editor.pos(item);
+        ui.horizontal(|ui| {
+            ui.label("Position:");
+            let x_changed = ui.add(egui::DragValue::new(&mut pos.x)).changed();
+            let y_changed = ui.add(egui::DragValue::new(&mut pos.y)).changed();
+            if x_changed || y_changed {
+                let delta = pos - editor.pos(item);
+                editor.move_item(item, delta);
+            }
+        });
+
+        let werewolf_amount = editor.map.werewolves.len();
+        match item {
+            EditorItem::Corral(i) => {
+                let corral = &mut editor.map.corrals[i];
+                ui.horizontal(|ui| {
+                    ui.label("Walls:");
+                    ui.add(egui::DragValue::new(&mut corral.length).range(3..=50));
+                    ui.label("x");
+                    ui.add(egui::DragValue::new(&mut corral.heigth).range(3..=50));
+                });
+                egui::ComboBox::from_label("Owner")
+                    .selected_text(format!("{:?}", corral.owner))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut corral.owner, CorralOwner::Player, "Player");
+                        for w in 0..werewolf_amount {
+                            ui.selectable_value(
+                                &mut corral.owner,
+                                CorralOwner::Werewolf(w),
+                                format!("Werewolf {}", w),
+                            );
+                        }
+                        ui.selectable_value(&mut corral.owner, CorralOwner::Nobody, "Nobody");
+                    });
+            }
+            EditorItem::Obstacle(i) => {
+                let obstacle = &mut editor.map.obstacles[i];
+                let default_radius = obstacle.kind.config(&config).radius;
+                egui::ComboBox::from_label("Kind")
+                    .selected_text(format!("{:?}", obstacle.kind))
+                    .show_ui(ui, |ui| {
+                        for kind in [ObstacleKind::Tree, ObstacleKind::Rock, ObstacleKind::Bush] {
+                            ui.selectable_value(&mut obstacle.kind, kind, format!("{:?}", kind));
+                        }
+                    });
+                let mut radius = obstacle.radius.unwrap_or(default_radius);
+                ui.horizontal(|ui| {
+                    ui.label("Radius:");
+                    if ui
+                        .add(egui::DragValue::new(&mut radius).range(1.0..=1000.0))
+                        .changed()
+                    {
+                        obstacle.radius = Some(radius);
+                    }
+                });
+            }
+            EditorItem::BiomeArea(i) => {
+                let area = &mut editor.map.biome_areas[i];
+                ui.horizontal(|ui| {
+                    ui.label("Biome:");
+                    biome_combo(ui, "area", &mut area.biome);
+                });
+                let mut size = area.max - area.min;
+                ui.horizontal(|ui| {
+                    ui.label("Size:");
+                    let w_changed = ui
+                        .add(egui::DragValue::new(&mut size.x).range(1.0..=20000.0))
+                        .changed();
+                    let h_changed = ui
+                        .add(egui::DragValue::new(&mut size.y).range(1.0..=20000.0))
+                        .changed();
+                    if w_changed || h_changed {
+                        let center = (area.min + area.max) / 2.;
+                        area.min = center - size / 2.;
+                        area.max = center + size / 2.;
+                    }
+                });
+            }
+            _ => {}
+        }
+
+        if !matches!(item, EditorItem::Player | EditorItem::PlayerBase)
+            && ui.button("Delete").clicked()
+        {
+            editor.remove(item);
+        }
+    });
+}
+
+fn biome_combo(ui: &mut egui::Ui, id: &str, biome: &mut Biome) {
+    egui::ComboBox::from_id_source(id)
+        .selected_text(format!("{:?}", biome))
+        .show_ui(ui, |ui| {
+            for candidate in Biome::ALL {
+                ui.selectable_value(biome, candidate, format!("{:?}", candidate));
+            }
+        });
+}
+
+// the broken map is not saved, so it can't break the game
+fn save_map(editor: &EditorMap, config: &GameConfig) -> String {
+    if let Err(err) = editor.map.validate(config) {
+        return format!("not saved, {}", err);
+    }
+
+    match editor.map.write(&editor.path) {
+        Ok(()) => format!("saved to {}", editor.path),
+        Err(err) => err.to_string(),
+    }
+}
+
+pub fn drag_editor_items(
+    mut context: EguiContexts,
+    mut editor: ResMut<EditorMap>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    window_q: Query<&Window, With<PrimaryWindow>>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    config: Res<GameConfig>,
+) {
+    if mouse.just_released(MouseButton::Left) {
+        editor.drag_from = None;
+    }
+
+    let Some(cursor) = window_q
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position())
+    else {
+        return;
+    };
+    let Some(cursor_pos) = camera_q
+        .get_single()
+        .ok()
+        .and_then(|(camera, c_transform)| camera.viewport_to_world_2d(c_transform, cursor))
+    else {
+        return;
+    };
+
+    if mouse.just_pressed(MouseButton::Left) && !context.ctx_mut().is_pointer_over_area() {
+        editor.selected = editor.item_at(cursor_pos, &config);
+        editor.drag_from = editor.selected.map(|_| cursor_pos);
+    }
+
+    if let (Some(item), Some(drag_from)) = (editor.selected, editor.drag_from) {
+        editor.move_item(item, cursor_pos - drag_from);
+        editor.drag_from = Some(cursor_pos);
+    }
+}
+
+pub fn move_editor_camera(
+    mut camera_q: Query<(&mut Transform, &OrthographicProjection), With<MainCamera>>,
+    mut context: EguiContexts,
+    input: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    config: Res<GameConfig>,
+) {
+    // the keys are for the text fields, while they are focused
+    if context.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    let Ok((mut c_pos, projection)) = camera_q.get_single_mut() else {
+        return;
+    };
+
+    let mut move_dir = Vec2::ZERO;
+    if input.pressed(config.player.key_up) {
+        move_dir += Vec2::new(0., 1.);
+    }
+    if input.pressed(config.player.key_down) {
+        move_dir += Vec2::new(0., -1.);
+    }
+    if input.pressed(config.player.key_left) {
+        move_dir += Vec2::new(-1., 0.);
+    }
+    if input.pressed(config.player.key_right) {
+        move_dir += Vec2::new(1., 0.);
+    }
+
+    // the camera is moving faster, when it is zoomed out
+    c_pos.translation +=
+        move_dir.extend(0.) * config.editor.camera_speed * projection.scale * time.delta_seconds();
+}
+
+pub fn draw_editor_map(mut gizmos: Gizmos, editor: Res<EditorMap>, config: Res<GameConfig>) {
+    let map = &editor.map;
+
+    gizmos.rect_2d(Vec2::ZERO, 0., Vec2::splat(map.size(&config)), css::WHITE);
+    for area in map.biome_areas.iter() {
+        gizmos.rect_2d(
+            (area.min + area.max) / 2.,
+            0.,
+            (area.max - area.min).abs(),
+            area.biome.config(&config).color,
+        );
+    }
+
+    for corral in map.corrals.iter() {
+        let color = match corral.owner {
+            CorralOwner::Player => config.player.catching_radius_color,
+            CorralOwner::Werewolf(_) => config.werewolf.color,
+            CorralOwner::Nobody => css::GRAY.into(),
+        };
+        gizmos.rect_2d(corral.pos, 0., corral_size(corral, &config), color);
+    }
+
+    gizmos.circle_2d(map.player_base, config.base.size / 2., config.base.color);
+    gizmos.circle_2d(
+        map.player_base,
+        config.base.catching_radius,
+        config.base.catching_radius_color,
+    );
+    for werewolf in map.werewolves.iter() {
+        gizmos.circle_2d(werewolf.base, config.base.size / 2., config.base.color);
+        gizmos.circle_2d(
+            werewolf.base,
+            config.base.catching_radius,
+            config.werewolf.catching_radius_color,
+        );
+        gizmos.circle_2d(
+            werewolf.werewolf,
+            config.werewolf.size / 2.,
+            config.werewolf.color,
+        );
+        // which base belongs to which werewolf
+        gizmos.line_2d(werewolf.werewolf, werewolf.base, config.werewolf.color);
+    }
+
+    for obstacle in map.obstacles.iter() {
+        gizmos.circle_2d(
+            obstacle.pos,
+            obstacle_radius(obstacle, &config),
+            obstacle.kind.config(&config).color,
+        );
+    }
+
+    gizmos.circle_2d(map.player, config.player.size / 2., css::WHITE);
+
+    if let Some(item) = editor.selected {
+        gizmos.rect_2d(
+            editor.pos(item),
+            0.,
+            editor.item_size(item, &config) + 10.,
+            css::YELLOW,
+        );
+    }
+}
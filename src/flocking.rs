@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+
+use crate::{
+    chicken::Chicken,
+    chicken_corral::ChickenCorral,
+    config::{FlockingConfig, GameConfig},
+    map::MapLayout,
+};
+
+// what the chicken sees from its neighbours, taken before anybody moved in this frame
+struct Boid {
+    pos: Vec2,
+    velocity: Vec2,
+    flock: Option<usize>,
+    panicking: bool,
+    aggressive: bool,
+}
+
+/// The chickens in the same corral are a flock.
+///
+/// The behaviour of the chicken is only saying, where it wants to go. Its neighbours are pushing
+/// it away, when they are too close (separation), it takes over a part of their velocity
+/// (alignment) and it goes to their center (cohesion). The panic is spreading through the flock.
+/// The aggressive chickens are not in any flock, they are jumping over the walls after the player.
+pub fn flock_chickens(
+    mut chickens_q: Query<(&mut Chicken, &mut Transform)>,
+    corrals_q: Query<(&Transform, &ChickenCorral), Without<Chicken>>,
+    time: Res<Time>,
+    layout: Res<MapLayout>,
+    config: Res<GameConfig>,
+) {
+    let flocks: Vec<(Rect, FlockingConfig)> = corrals_q
+        .iter()
+        .map(|(c_pos, corral)| {
+            (
+                corral.rect(c_pos.translation.xy(), &config),
+                corral.flocking.unwrap_or(config.chicken.flocking),
+            )
+        })
+        .collect();
+
+    let boids: Vec<Boid> = chickens_q
+        .iter()
+        .map(|(chicken, ch_pos)| {
+            let pos = ch_pos.translation.xy();
+            Boid {
+                pos,
+                velocity: chicken.velocity,
+                flock: flocks.iter().position(|(rect, _)| rect.contains(pos)),
+                panicking: chicken.is_panicking(),
+                aggressive: chicken.is_aggressive(),
+            }
+        })
+        .collect();
+
+    for (i, (mut chicken, mut ch_pos)) in chickens_q.iter_mut().enumerate() {
+        let boid = &boids[i];
+        let ground_speed = layout.speed_multiplier_at(boid.pos, &config);
+
+        if boid.aggressive {
+            ch_pos.translation += chicken.velocity.extend(0.) * ground_speed * time.delta_seconds();
+            continue;
+        }
+
+        // the chickens outside of the corrals are a flock too
+        let flocking = boid
+            .flock
+            .map_or(config.chicken.flocking, |flock| flocks[flock].1);
+        let neighbours: Vec<(usize, &Boid)> = boids
+            .iter()
+            .enumerate()
+            .filter(|(j, other)| {
+                *j != i
+                    && !other.aggressive
+                    && other.flock == boid.flock
+                    && other.pos.distance(boid.pos) < flocking.neighbour_radius
+            })
+            .collect();
+
+        // it is enough to see the others running away
+        if !chicken.is_panicking() && chicken.can_panic() {
+            if let Some((_, panicking)) = neighbours
+                .iter()
+                .find(|(_, other)| other.panicking && other.velocity != Vec2::ZERO)
+            {
+                chicken.panic(panicking.velocity.normalize(), &config);
+                chicken.velocity = panicking.velocity.normalize() * config.chicken.panic_speed;
+            }
+        }
+
+        let mut steering = Vec2::ZERO;
+        if !neighbours.is_empty() {
+            let count = neighbours.len() as f32;
+            let center = neighbours.iter().map(|(_, other)| other.pos).sum::<Vec2>() / count;
+            let average_velocity = neighbours
+                .iter()
+                .map(|(_, other)| other.velocity)
+                .sum::<Vec2>()
+                / count;
+
+            let separation: Vec2 = neighbours
+                .iter()
+                .filter(|(_, other)| other.pos.distance(boid.pos) < flocking.separation_distance)
+                .map(|(j, other)| {
+                    let away = boid.pos - other.pos;
+                    // the chickens on the same place are pushed apart in different directions
+                    away.try_normalize()
+                        .unwrap_or_else(|| Vec2::from_angle(*j as f32 - i as f32))
+                        * (1. - away.length() / flocking.separation_distance)
+                })
+                .sum();
+
+            steering += separation * flocking.separation
+                + (average_velocity - chicken.velocity) * flocking.alignment
+                + (center - boid.pos) * flocking.cohesion;
+        }
+
+        let velocity = (chicken.velocity + steering).clamp_length_max(flocking.max_speed);
+        chicken.velocity = velocity;
+        ch_pos.translation += velocity.extend(0.) * ground_speed * time.delta_seconds();
+    }
+}
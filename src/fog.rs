@@ -0,0 +1,186 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chicken::Chicken,
+    config::GameConfig,
+    map::{is_hidden_in_bush, MapLayout, Obstacle},
+    player::Player,
+    state::{GameState, InRound, SpawnSet},
+    werewolf::Werewolf,
+};
+
+/// The fog of war.
+///
+/// The arena is covered with the fog tiles (they have the same size as the ground tiles). The
+/// tiles in the sight of the player are clear, the explored ones are only dimmed and the rest is
+/// covered completely. The chickens and the werewolves are hidden outside of the sight, the
+/// bases, corrals and obstacles are staying under the dimmed fog, once they were explored.
+pub struct FogPlugin;
+
+impl Plugin for FogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>();
+        app.init_resource::<MapLayout>();
+        app.init_resource::<Explored>();
+
+        app.add_systems(OnEnter(InRound), spawn_fog.in_set(SpawnSet::Decorate));
+        app.add_systems(
+            Update,
+            ((explore, update_fog_tiles).chain(), hide_out_of_sight)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// The ground tiles, which the player has ever seen in this round
+#[derive(Resource, Serialize, Deserialize, Clone, Default)]
+pub struct Explored {
+    tiles_per_side: usize,
+    // row by row like the tiles of the MapLayout
+    tiles: Vec<bool>,
+}
+
+impl Explored {
+    pub fn new(layout: &MapLayout) -> Self {
+        let tiles_per_side = layout.tiles_per_side();
+        Self {
+            tiles_per_side,
+            tiles: vec![false; tiles_per_side * tiles_per_side],
+        }
+    }
+
+    pub fn is_explored(&self, x: usize, y: usize) -> bool {
+        self.tiles
+            .get(y * self.tiles_per_side + x)
+            .copied()
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Component)]
+pub struct FogTile {
+    pub x: usize,
+    pub y: usize,
+}
+
+pub fn in_sight(player: &Player, p_pos: Vec2, pos: Vec2, config: &GameConfig) -> bool {
+    p_pos.distance(pos) <= player.sight_radius(config)
+}
+
+pub fn spawn_fog_tiles(commands: &mut Commands, layout: &MapLayout, config: &GameConfig) {
+    for y in 0..layout.tiles_per_side() {
+        for x in 0..layout.tiles_per_side() {
+            commands.spawn((
+                SpriteBundle {
+                    transform: Transform::from_translation(
+                        layout.tile_center(x, y).extend(config.fog.z),
+                    ),
+                    sprite: Sprite {
+                        color: config.fog.color,
+                        custom_size: Some(Vec2::splat(layout.tile_size)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                FogTile { x, y },
+                StateScoped(InRound),
+            ));
+        }
+    }
+}
+
+pub fn spawn_fog(mut commands: Commands, layout: Res<MapLayout>, config: Res<GameConfig>) {
+    commands.insert_resource(Explored::new(&layout));
+    spawn_fog_tiles(&mut commands, &layout, &config);
+}
+
+pub fn explore(
+    mut explored: ResMut<Explored>,
+    player_q: Query<(&Transform, &Player)>,
+    layout: Res<MapLayout>,
+    config: Res<GameConfig>,
+) {
+    let Ok((p_pos, player)) = player_q.get_single() else {
+        return;
+    };
+    let p_pos = p_pos.translation.xy();
+    // the loaded game can have the other map
+    if explored.tiles_per_side != layout.tiles_per_side() {
+        *explored = Explored::new(&layout);
+    }
+
+    for y in 0..explored.tiles_per_side {
+        for x in 0..explored.tiles_per_side {
+            let i = y * explored.tiles_per_side + x;
+            if !explored.tiles[i] && in_sight(player, p_pos, layout.tile_center(x, y), &config) {
+                explored.tiles[i] = true;
+            }
+        }
+    }
+}
+
+pub fn update_fog_tiles(
+    mut fog_q: Query<(&FogTile, &mut Sprite, &mut Visibility)>,
+    player_q: Query<(&Transform, &Player)>,
+    explored: Res<Explored>,
+    layout: Res<MapLayout>,
+    config: Res<GameConfig>,
+) {
+    let Ok((p_pos, player)) = player_q.get_single() else {
+        return;
+    };
+    let p_pos = p_pos.translation.xy();
+    let explored_color = config
+        .fog
+        .color
+        .with_alpha(config.fog.color.alpha() * config.fog.explored_alpha);
+
+    for (tile, mut sprite, mut visibility) in fog_q.iter_mut() {
+        if in_sight(player, p_pos, layout.tile_center(tile.x, tile.y), &config) {
+            visibility.set_if_neq(Visibility::Hidden);
+            continue;
+        }
+        visibility.set_if_neq(Visibility::Inherited);
+
+        let color = if explored.is_explored(tile.x, tile.y) {
+            explored_color
+        } else {
+            config.fog.color
+        };
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
+
+/// The werewolves are hidden also in the bushes here (instead of in `hide_werewolves_in_bushes`),
+/// so their visibility is not switched back and forth every frame
+#[allow(clippy::type_complexity)]
+pub fn hide_out_of_sight(
+    mut hidden_q: Query<
+        (&Transform, &mut Visibility, Has<Werewolf>),
+        Or<(With<Chicken>, With<Werewolf>)>,
+    >,
+    player_q: Query<(&Transform, &Player)>,
+    obstacles_q: Query<(&Transform, &Obstacle)>,
+    config: Res<GameConfig>,
+) {
+    let Ok((p_pos, player)) = player_q.get_single() else {
+        return;
+    };
+    let p_pos = p_pos.translation.xy();
+
+    for (pos, mut visibility, is_werewolf) in hidden_q.iter_mut() {
+        let pos = pos.translation.xy();
+        // the werewolf can be in the sight, but still hidden in a bush
+        let hidden = !in_sight(player, p_pos, pos, &config)
+            || is_werewolf && is_hidden_in_bush(pos, Some(p_pos), &obstacles_q, &config);
+        let next_visibility = if hidden {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+        visibility.set_if_neq(next_visibility);
+    }
+}
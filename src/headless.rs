@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use bevy::{input::InputPlugin, prelude::*, state::app::StatesPlugin, time::TimeUpdateStrategy};
+
+use crate::{
+    base::Base, player::ForPlayer, rng::GameRng, round::RoundSummary, state::GameState,
+    werewolf::ForWerewolf,
+};
+
+/// Everything the game logic needs to run without a window or a gpu.
+///
+/// The time is not taken from the clock, every `app.update()` moves the game exactly one `tick`
+/// forward, so thousands of ticks can be simulated as fast as possible and the same amount of
+/// ticks is always the same amount of game time.
+///
+/// Use it together with the game plugins, which are not rendering anything:
+/// `GamePlugins.build().disable::<CameraPlugin>().disable::<UiPlugin>()`. There is no menu, the
+/// round is started right away.
+pub struct HeadlessPlugin {
+    pub tick: Duration,
+}
+
+impl Default for HeadlessPlugin {
+    fn default() -> Self {
+        Self {
+            tick: Duration::from_secs_f64(1. / 60.),
+        }
+    }
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            InputPlugin,
+            TransformPlugin,
+            HierarchyPlugin,
+            StatesPlugin,
+        ));
+        // the meshes and materials are still created by the spawning systems, they are just
+        // never rendered
+        app.init_asset::<Mesh>();
+        app.init_asset::<ColorMaterial>();
+
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(self.tick));
+    }
+
+    // the game state is initialized by the game plugins, so it can be overwritten only after
+    // all of them were built
+    fn finish(&self, app: &mut App) {
+        app.insert_state(GameState::Playing);
+    }
+}
+
+/// Runs the app for the given amount of ticks or until the round is over, returns how many ticks
+/// were simulated
+pub fn simulate(app: &mut App, ticks: usize) -> usize {
+    for tick in 0..ticks {
+        app.update();
+
+        if *app.world().resource::<State<GameState>>().get() == GameState::GameOver {
+            return tick + 1;
+        }
+    }
+
+    ticks
+}
+
+/// Prints how many chickens are in the player base and in every werewolf base, after the end of
+/// the round the bases are despawned, so the summary of the round is printed
+pub fn print_bases(world: &mut World) {
+    if let Some(rng) = world.get_resource::<GameRng>() {
+        println!("seed: {}", rng.seed());
+    }
+
+    if let Some(summary) = world.get_resource::<RoundSummary>() {
+        println!(
+            "round {:?} ({:?}) after {:.1} seconds",
+            summary.result, summary.reason, summary.time
+        );
+        println!("player base: {} chickens", summary.player_chickens);
+        for (i, chickens) in summary.werewolf_chickens.iter().enumerate() {
+            println!("werewolf base {}: {} chickens", i, chickens);
+        }
+        return;
+    }
+
+    let mut player_q = world.query_filtered::<&Base, With<ForPlayer>>();
+    for base in player_q.iter(world) {
+        println!("player base: {} chickens", base.chickens_amount);
+    }
+
+    let mut werewolf_q = world.query_filtered::<&Base, With<ForWerewolf>>();
+    for (i, base) in werewolf_q.iter(world).enumerate() {
+        println!("werewolf base {}: {} chickens", i, base.chickens_amount);
+    }
+}
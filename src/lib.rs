@@ -0,0 +1,128 @@
+pub mod ai;
+pub mod base;
+pub mod camera;
+pub mod chicken;
+pub mod chicken_corral;
+pub mod collision;
+pub mod config;
+pub mod editor;
+pub mod flocking;
+pub mod fog;
+pub mod headless;
+pub mod map;
+pub mod map_file;
+pub mod minimap;
+pub mod misc;
+pub mod navigation;
+pub mod player;
+pub mod replay;
+pub mod rng;
+pub mod round;
+pub mod save;
+pub mod settings;
+pub mod spawning;
+pub mod state;
+pub mod ui;
+pub mod werewolf;
+pub mod werewolf_ai;
+
+use bevy::{app::PluginGroupBuilder, prelude::*, time::Stopwatch};
+
+pub use base::BasePlugin;
+pub use camera::CameraPlugin;
+pub use chicken::ChickenPlugin;
+pub use chicken_corral::CorralPlugin;
+pub use collision::CollisionPlugin;
+pub use config::ConfigPlugin;
+pub use editor::EditorPlugin;
+pub use fog::FogPlugin;
+pub use headless::HeadlessPlugin;
+pub use map::MapPlugin;
+pub use minimap::MinimapPlugin;
+pub use navigation::NavigationPlugin;
+pub use player::PlayerPlugin;
+pub use replay::ReplayPlugin;
+pub use rng::RngPlugin;
+pub use round::RoundPlugin;
+pub use save::SavePlugin;
+pub use state::GameStatePlugin;
+pub use ui::UiPlugin;
+pub use werewolf::WerewolfPlugin;
+
+use config::GameConfig;
+
+/// All the game plugins together, every one of them can be disabled or replaced with
+/// `GamePlugins.build().disable::<UiPlugin>()` and so on, only the GameStatePlugin is needed by
+/// all the others.
+///
+/// The window, rendering and egui plugins are not in here (the UiPlugin and the EditorPlugin need
+/// the egui), they must be added by the app itself
+pub struct GamePlugins;
+
+impl PluginGroup for GamePlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(ConfigPlugin)
+            .add(RngPlugin)
+            .add(GameStatePlugin)
+            .add(CameraPlugin)
+            .add(MinimapPlugin)
+            .add(PlayerPlugin)
+            .add(BasePlugin)
+            .add(CorralPlugin)
+            .add(ChickenPlugin)
+            .add(WerewolfPlugin)
+            .add(MapPlugin)
+            .add(CollisionPlugin)
+            .add(NavigationPlugin)
+            .add(FogPlugin)
+            .add(RoundPlugin)
+            .add(SavePlugin)
+            .add(ReplayPlugin)
+            .add(UiPlugin)
+            .add(EditorPlugin)
+    }
+}
+
+#[derive(Resource)]
+pub struct Game {
+    pub chicken_spawn_timer: Timer,
+    pub lvlup_screen_opened: bool,
+    /// how long the current round is going, without the pauses
+    pub round_time: Stopwatch,
+}
+
+// get rid of it and pack this in to the player
+#[derive(Resource)]
+pub struct PlayerRes {
+    pub inventory_chickens_amount: usize,
+    pub catchable_chicken: Option<Entity>,
+}
+
+impl Default for PlayerRes {
+    fn default() -> Self {
+        Self {
+            inventory_chickens_amount: 0,
+            catchable_chicken: None,
+        }
+    }
+}
+
+impl Game {
+    pub fn from_config(config: &GameConfig) -> Self {
+        Self {
+            chicken_spawn_timer: Timer::from_seconds(
+                config.chicken_spawn_delta,
+                TimerMode::Repeating,
+            ),
+            lvlup_screen_opened: false,
+            round_time: Stopwatch::new(),
+        }
+    }
+}
+
+impl FromWorld for Game {
+    fn from_world(world: &mut World) -> Self {
+        Game::from_config(&world.get_resource_or_insert_with(GameConfig::default))
+    }
+}
@@ -1,125 +1,95 @@
-mod base;
-mod camera;
-mod chicken;
-mod chicken_corral;
-mod map;
-mod minimap;
-mod misc;
-mod player;
-mod settings;
-mod spawning;
-mod ui;
-mod werewolf;
-
-use base::change_base_text;
 use bevy::prelude::*;
 use bevy_egui::EguiPlugin;
-use camera::{move_camera, spawn_camera, zoom_camera};
-use chicken::{behave_chickens, chicken_corral_collision};
-use chicken_corral::{assign_player_to_corral, assign_werewolf_to_corral};
-use player::{
-    catch_chicken, move_player, on_add_catchable, on_remove_catchable, player_chicken_collision,
-    player_lvlup, try_give_chickens_to_base, EvPlayerLvlup,
-};
-use settings::*;
-use spawning::{
-    spawn_chicken_in_corrals, spawn_corral_walls, spawn_player, spawn_player_base,
-    spawn_player_corral, spawn_werewolf_with_base_and_corrals,
+use my_3_game::{
+    config::{ConfigWatcher, GameConfig, DEFAULT_CONFIG_PATH},
+    headless::{print_bases, simulate},
+    replay::{Replay, ReplayPlayer, ReplayRecorder},
+    CameraPlugin, EditorPlugin, GamePlugins, HeadlessPlugin, MinimapPlugin, UiPlugin,
 };
-use ui::{change_ui, cleanup_popups, lvl_up_screen, popup, spawn_ui, EvSpawnPopup};
-use werewolf::{change_werewolf_text, werewolf_behave};
+
+const DEFAULT_HEADLESS_TICKS: usize = 10000;
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
     let mut app = App::new();
 
-    app.add_event::<EvSpawnPopup>();
-    app.add_event::<EvPlayerLvlup>();
-    app.add_plugins((DefaultPlugins, EguiPlugin));
-
-    app.insert_resource(Game::default());
-    app.insert_resource(PlayerRes::default());
+    // the config must be there before the plugins are added, some resources are made from it
+    let config_path = arg_value(&args, "--config").or_else(|| {
+        std::path::Path::new(DEFAULT_CONFIG_PATH)
+            .exists()
+            .then_some(DEFAULT_CONFIG_PATH)
+    });
+    let replay = arg_value(&args, "--replay")
+        .map(|path| Replay::load(path).unwrap_or_else(|err| panic!("{}: {}", path, err)));
+    let mut config = match (&replay, config_path) {
+        // the replay is played with the config and the seed, with which it was recorded
+        (Some(replay), _) => replay.seeded_config(),
+        (None, Some(path)) => {
+            // changes in the file are applied while the game is running
+            app.insert_resource(ConfigWatcher::new(path));
+            GameConfig::load(path).unwrap_or_else(|err| panic!("{}: {}", path, err))
+        }
+        (None, None) => GameConfig::default(),
+    };
+    // the round can be reproduced with `--seed`, the seed of every round is logged
+    if let Some(seed) = arg_value(&args, "--seed") {
+        config.seed = Some(seed.parse().expect("--seed must be a number"));
+    }
+    // `--map maps/tutorial.ron` plays the hand made map instead of the generated one
+    if let Some(path) = arg_value(&args, "--map") {
+        config.map.file = Some(path.to_string());
+    }
+    app.insert_resource(config);
 
-    app.add_systems(
-        Startup,
-        (
-            spawn_camera,
-            spawn_player,
-            spawn_ui,
-            spawn_player_base,
-            spawn_werewolf_with_base_and_corrals,
-            spawn_player_corral,
-        ),
-    );
-    app.add_systems(
-        PostStartup,
-        (assign_player_to_corral, assign_werewolf_to_corral).chain(),
-    );
-    app.add_systems(PostStartup, spawn_corral_walls);
+    // `--record replay.ron` writes the last round to the file, `--replay replay.ron` plays it
+    let replay_ticks = replay.as_ref().map(|replay| replay.frames.len() + 1);
+    if let Some(replay) = replay {
+        app.insert_resource(ReplayPlayer::new(replay));
+    }
+    if let Some(path) = arg_value(&args, "--record") {
+        app.insert_resource(ReplayRecorder::new(path));
+    }
 
-    // chicken systems
-    app.add_systems(
-        Update,
-        (
-            behave_chickens,
-            spawn_chicken_in_corrals,
-            chicken_corral_collision,
-        ),
-    );
-    // camera systems
-    app.add_systems(Update, (move_camera, zoom_camera));
-    // player systems
-    app.add_systems(Update, (move_player, move_camera, player_lvlup));
-    app.add_systems(
-        FixedUpdate,
-        (
-            player_chicken_collision,
-            catch_chicken,
-            try_give_chickens_to_base,
-        )
-            .chain(),
-    );
-    // ui systems
-    app.add_systems(Update, (popup, cleanup_popups, lvl_up_screen));
-    app.add_systems(Update, (change_ui).run_if(resource_changed::<Game>));
-    // base systems
-    app.add_systems(Update, change_base_text);
-    // werewolf systems
-    app.add_systems(Update, (werewolf_behave, change_werewolf_text));
+    // running without window, for example: `my_3_game --headless --ticks 36000`
+    if has_flag(&args, "--headless") {
+        let ticks = arg_value(&args, "--ticks")
+            .map(|ticks| ticks.parse().expect("--ticks must be a number"))
+            // the first tick is starting the round of the replay
+            .or(replay_ticks)
+            .unwrap_or(DEFAULT_HEADLESS_TICKS);
 
-    // chicken observers
-    app.observe(on_add_catchable);
-    app.observe(on_remove_catchable);
+        app.add_plugins(HeadlessPlugin::default());
+        app.add_plugins(
+            GamePlugins
+                .build()
+                .disable::<CameraPlugin>()
+                .disable::<MinimapPlugin>()
+                .disable::<UiPlugin>()
+                .disable::<EditorPlugin>(),
+        );
+        app.finish();
+        app.cleanup();
 
-    app.run();
-}
+        let simulated = simulate(&mut app, ticks);
+        println!("simulated {} ticks", simulated);
+        print_bases(app.world_mut());
+        return;
+    }
 
-#[derive(Resource)]
-struct Game {
-    chicken_spawn_timer: Timer,
-    lvlup_screen_opened: bool,
-}
+    app.add_plugins((DefaultPlugins, EguiPlugin));
+    app.add_plugins(GamePlugins);
 
-// get rid of it and pack this in to the player
-#[derive(Resource)]
-struct PlayerRes {
-    inventory_chickens_amount: usize,
-    catchable_chicken: Option<Entity>,
+    app.run();
 }
 
-impl Default for PlayerRes {
-    fn default() -> Self {
-        Self {
-            inventory_chickens_amount: 0,
-            catchable_chicken: None,
-        }
-    }
+fn has_flag(args: &[String], flag: &str) -> bool {
+    args.iter().any(|arg| arg == flag)
 }
 
-impl Default for Game {
-    fn default() -> Self {
-        Self {
-            chicken_spawn_timer: Timer::from_seconds(CHICKEN_SPAWN_DELTA, TimerMode::Repeating),
-            lvlup_screen_opened: false,
-        }
-    }
+// the value after the flag, `--ticks 100` gives "100"
+fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
 }
@@ -0,0 +1,696 @@
+use std::f32::consts::SQRT_2;
+
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    base::Base,
+    chicken::Chicken,
+    chicken_corral::ChickenCorral,
+    config::{BiomeConfig, FlockingConfig, GameConfig, ObstacleConfig},
+    flocking::flock_chickens,
+    fog::Explored,
+    map_file::{MapError, MapFile},
+    player::{move_player, respawn_player, Player},
+    rng::{reset_rng, GameRng},
+    state::{GameState, InRound, SpawnSet},
+    werewolf::{werewolf_behave, Werewolf},
+};
+
+/// How many random places are tried for every obstacle, before it is left out
+const MAX_PLACING_TRIES: usize = 20;
+
+/// The map of the round.
+///
+/// Before anything is spawned, the MapLayout is generated from the GameRng: the ground of the
+/// arena with its biomes and the places of the entities on the spawning rings (see
+/// spawning.rs), so the spawning systems are only taking the places from it. Nothing can leave
+/// the arena.
+///
+/// The obstacles are placed randomly after all the entities of the spawning rings are there, so
+/// they are never on the bases, corrals or the entities itself. The trees and the rocks are
+/// blocking the way, the bushes are only hiding the werewolves in them.
+pub struct MapPlugin;
+
+impl Plugin for MapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>();
+        app.init_resource::<GameRng>();
+        app.init_resource::<MapLayout>();
+        app.init_resource::<PendingMapFile>();
+
+        app.add_systems(
+            OnEnter(InRound),
+            generate_map_layout
+                .after(reset_rng)
+                .before(SpawnSet::Entities),
+        );
+        // the walls are spawned in the same set, but they are not using the random numbers
+        app.add_systems(
+            OnEnter(InRound),
+            (spawn_ground, spawn_obstacles).in_set(SpawnSet::Decorate),
+        );
+        app.add_systems(
+            Update,
+            (
+                // everything is pushed back after it was moved in this frame
+                keep_in_arena
+                    .after(move_player)
+                    .after(respawn_player)
+                    .after(werewolf_behave)
+                    .after(flock_chickens),
+                // the fog is hiding the werewolves in the bushes together with the ones out of
+                // the sight, so the visibility is set only once per frame
+                hide_werewolves_in_bushes.run_if(not(resource_exists::<Explored>)),
+            )
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy, Default)]
+pub enum Biome {
+    #[default]
+    Grass,
+    Mud,
+    Forest,
+}
+
+impl Biome {
+    pub const ALL: [Biome; 3] = [Biome::Grass, Biome::Mud, Biome::Forest];
+
+    pub fn config<'a>(&self, config: &'a GameConfig) -> &'a BiomeConfig {
+        match self {
+            Biome::Grass => &config.map.grass,
+            Biome::Mud => &config.map.mud,
+            Biome::Forest => &config.map.forest,
+        }
+    }
+}
+
+/// The places of one werewolf with its base
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WerewolfPlaces {
+    pub werewolf: Vec2,
+    pub base: Vec2,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CorralOwner {
+    Player,
+    /// the index of the werewolf in the layout
+    Werewolf(usize),
+    /// the chickens are spawned in there, but nobody is going to catch them
+    Nobody,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CorralPlace {
+    pub pos: Vec2,
+    pub length: usize,
+    pub heigth: usize,
+    pub owner: CorralOwner,
+    /// the chickens in this corral are steered differently than the config says
+    #[serde(default)]
+    pub flocking: Option<FlockingConfig>,
+}
+
+impl CorralPlace {
+    pub fn component(&self, belongs_to: Option<Entity>) -> ChickenCorral {
+        ChickenCorral {
+            belongs_to,
+            length: self.length,
+            heigth: self.heigth,
+            flocking: self.flocking,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ObstaclePlace {
+    pub pos: Vec2,
+    pub obstacle: Obstacle,
+}
+
+/// The map of the round, it is generated or loaded from the map file (see map_file.rs).
+///
+/// The arena is a square with the center in (0, 0), which is divided in the tiles with the
+/// biomes
+#[derive(Resource, Serialize, Deserialize, Clone, Default)]
+pub struct MapLayout {
+    pub size: f32,
+    pub tile_size: f32,
+    tiles_per_side: usize,
+    // row by row, starting from the bottom left corner
+    tiles: Vec<Biome>,
+    pub player: Vec2,
+    pub player_base: Vec2,
+    pub werewolves: Vec<WerewolfPlaces>,
+    pub corrals: Vec<CorralPlace>,
+    /// the obstacles, which are always on the same place
+    pub obstacles: Vec<ObstaclePlace>,
+    /// if the other obstacles from the config are placed randomly around everything
+    pub random_obstacles: bool,
+}
+
+impl MapLayout {
+    /// The layout without any entities, the ground is taken from the biome_at
+    pub fn with_ground(size: f32, tile_size: f32, biome_at: impl Fn(Vec2) -> Biome) -> Self {
+        let tiles_per_side = (size / tile_size).ceil() as usize;
+        let mut layout = Self {
+            size,
+            tile_size,
+            tiles_per_side,
+            tiles: Vec::with_capacity(tiles_per_side * tiles_per_side),
+            ..Default::default()
+        };
+        for y in 0..tiles_per_side {
+            for x in 0..tiles_per_side {
+                let biome = biome_at(layout.tile_center(x, y));
+                layout.tiles.push(biome);
+            }
+        }
+
+        layout
+    }
+
+    /// Fails, when the entities can't be placed in the arena without overlapping
+    pub fn generate(config: &GameConfig, rng: &mut GameRng) -> Result<Self, MapError> {
+        let map = &config.map;
+
+        // every tile gets the biome of the nearest biome center
+        let total_weight: u32 = Biome::ALL
+            .iter()
+            .map(|biome| biome.config(config).weight)
+            .sum();
+        let biome_centers: Vec<(Vec2, Biome)> = (0..map.biome_amount)
+            .map(|_| {
+                let center = Vec2::new(
+                    rng.gen_range(-map.size / 2.0..map.size / 2.0),
+                    rng.gen_range(-map.size / 2.0..map.size / 2.0),
+                );
+                let mut roll = rng.gen_range(0..total_weight.max(1));
+                let mut biome = Biome::Grass;
+                for candidate in Biome::ALL {
+                    let weight = candidate.config(config).weight;
+                    if roll < weight {
+                        biome = candidate;
+                        break;
+                    }
+                    roll -= weight;
+                }
+                (center, biome)
+            })
+            .collect();
+
+        let mut layout = Self::with_ground(map.size, map.tile_size, |tile_pos| {
+            biome_centers
+                .iter()
+                .min_by(|(a, _), (b, _)| {
+                    a.distance_squared(tile_pos)
+                        .total_cmp(&b.distance_squared(tile_pos))
+                })
+                .map_or(Biome::Grass, |(_, biome)| *biome)
+        });
+        layout.random_obstacles = true;
+
+        layout.place_entities(config, rng)?;
+
+        Ok(layout)
+    }
+
+    // the places are chosen on the rings like before, but every one of them is checked against
+    // the places, which are already taken, so nothing is overlapping
+    fn place_entities(&mut self, config: &GameConfig, rng: &mut GameRng) -> Result<(), MapError> {
+        let spawning = &config.spawning;
+        let corral_radius = corral_footprint(
+            config,
+            config.corral.default_length,
+            config.corral.default_heigth,
+        );
+        let (length, heigth) = (config.corral.default_length, config.corral.default_heigth);
+        let mut taken: Vec<(Vec2, f32)> = Vec::new();
+
+        self.player = Vec2::ZERO;
+        taken.push((self.player, config.player.size / 2.));
+
+        self.player_base = place_on_ring(
+            self.player,
+            spawning.base_distance_from_entity + config.player.size,
+            0.0..360.0,
+            config.base.catching_radius,
+            self.size,
+            &mut taken,
+            rng,
+        )?;
+        self.corrals.push(CorralPlace {
+            pos: place_on_ring(
+                Vec2::ZERO,
+                spawning.p_corral_distance_from_center,
+                0.0..360.0,
+                corral_radius,
+                self.size,
+                &mut taken,
+                rng,
+            )?,
+            length,
+            heigth,
+            owner: CorralOwner::Player,
+            flocking: None,
+        });
+
+        let angle_step = 360.0 / spawning.werewolf_amount as f32;
+        for i in 0..spawning.werewolf_amount {
+            // the werewolf and its corral are in its own part of the circle
+            let angles = (angle_step + spawning.angle_margin / 2.) * i as f32
+                ..(angle_step - spawning.angle_margin / 2.) * (i + 1) as f32;
+
+            let werewolf = place_on_ring(
+                Vec2::ZERO,
+                spawning.werewolf_distance_to_center,
+                angles.clone(),
+                config.werewolf.size / 2.,
+                self.size,
+                &mut taken,
+                rng,
+            )?;
+            let base = place_on_ring(
+                werewolf,
+                spawning.base_distance_from_entity,
+                0.0..360.0,
+                config.base.catching_radius,
+                self.size,
+                &mut taken,
+                rng,
+            )?;
+            let corral = place_on_ring(
+                Vec2::ZERO,
+                spawning.w_corral_distance_from_center,
+                angles,
+                corral_radius,
+                self.size,
+                &mut taken,
+                rng,
+            )?;
+
+            self.werewolves.push(WerewolfPlaces { werewolf, base });
+            self.corrals.push(CorralPlace {
+                pos: corral,
+                length,
+                heigth,
+                owner: CorralOwner::Werewolf(i),
+                flocking: None,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// The corrals of this owner
+    pub fn corrals_of(&self, owner: CorralOwner) -> impl Iterator<Item = &CorralPlace> {
+        self.corrals
+            .iter()
+            .filter(move |corral| corral.owner == owner)
+    }
+
+    pub fn tile_center(&self, x: usize, y: usize) -> Vec2 {
+        Vec2::new(
+            -self.size / 2. + (x as f32 + 0.5) * self.tile_size,
+            -self.size / 2. + (y as f32 + 0.5) * self.tile_size,
+        )
+    }
+
+    pub fn tiles_per_side(&self) -> usize {
+        self.tiles_per_side
+    }
+
+    /// The biome under the position, None outside of the arena
+    pub fn biome_at(&self, pos: Vec2) -> Option<Biome> {
+        let tile = (pos + self.size / 2.) / self.tile_size;
+        if tile.x < 0. || tile.y < 0. {
+            return None;
+        }
+        let (x, y) = (tile.x as usize, tile.y as usize);
+        if x >= self.tiles_per_side || y >= self.tiles_per_side {
+            return None;
+        }
+
+        self.tiles.get(y * self.tiles_per_side + x).copied()
+    }
+
+    /// How fast everything is moving on this position
+    pub fn speed_multiplier_at(&self, pos: Vec2, config: &GameConfig) -> f32 {
+        self.biome_at(pos)
+            .map_or(1.0, |biome| biome.config(config).speed_multiplier)
+    }
+
+    /// The farthest position from the center, where the thing with this size is still fully in
+    /// the arena
+    pub fn half_extent(&self, size: f32) -> f32 {
+        (self.size / 2. - size / 2.).max(0.)
+    }
+}
+
+/// The corral as a circle around its center
+pub fn corral_footprint(config: &GameConfig, length: usize, heigth: usize) -> f32 {
+    let half_side = config.corral.wall_length * length.max(heigth) as f32 / 2.;
+    half_side * SQRT_2 + config.corral.wall_length
+}
+
+// tries the random angles on the ring, if all of them are taken, the ring is getting bigger until
+// it is leaving the arena, the place must be fully in the arena
+fn place_on_ring(
+    center: Vec2,
+    distance: f32,
+    angles: std::ops::Range<f32>,
+    radius: f32,
+    arena_size: f32,
+    taken: &mut Vec<(Vec2, f32)>,
+    rng: &mut GameRng,
+) -> Result<Vec2, MapError> {
+    let half_extent = arena_size / 2. - radius;
+    // farther than the farthest corner of the arena nothing is in it anymore
+    let max_distance = (center.abs() + arena_size / 2.).length();
+
+    let mut distance = distance;
+    while distance <= max_distance {
+        for _ in 0..MAX_PLACING_TRIES {
+            let angle = rng.gen_range(angles.clone()).to_radians();
+            let pos = center + Vec2::new(angle.sin(), angle.cos()) * distance;
+
+            let in_arena = pos.abs().max_element() <= half_extent;
+            let is_free = taken
+                .iter()
+                .all(|(taken_pos, taken_radius)| pos.distance(*taken_pos) >= radius + taken_radius);
+            if in_arena && is_free {
+                taken.push((pos, radius));
+                return Ok(pos);
+            }
+        }
+        distance += radius.max(1.);
+    }
+
+    Err(MapError::Invalid(format!(
+        "map size ({}) is too small, there is no free place for everything",
+        arena_size
+    )))
+}
+
+/// The map file chosen in the editor, it is played instead of the one from the config. It is
+/// kept apart, so the config is not changed (and reloaded) by the editor
+#[derive(Resource, Default)]
+pub struct PendingMapFile(pub Option<String>);
+
+impl PendingMapFile {
+    /// The map file of the next round, None for the generated map
+    pub fn path<'a>(&'a self, config: &'a GameConfig) -> Option<&'a String> {
+        self.0.as_ref().or(config.map.file.as_ref())
+    }
+}
+
+/// Loads the map file from the editor or the config, or generates the new map, if there is no file
+pub fn generate_map_layout(
+    mut layout: ResMut<MapLayout>,
+    mut rng: ResMut<GameRng>,
+    mut next_state: ResMut<NextState<GameState>>,
+    pending: Res<PendingMapFile>,
+    config: Res<GameConfig>,
+) {
+    if let Some(path) = pending.path(&config) {
+        // the file is read again every round, so the designers can change it between the rounds
+        match MapFile::load(path, &config) {
+            Ok(map_file) => {
+                *layout = map_file.to_layout(&config);
+                info!("map loaded from {}", path);
+                return;
+            }
+            // the round is going on with the generated map
+            Err(err) => error!("{}: {}", path, err),
+        }
+    }
+
+    match MapLayout::generate(&config, &mut rng) {
+        Ok(generated) => *layout = generated,
+        // the round can't be played, only the player and its base are spawned in the middle and
+        // the game is going back to the menu right away
+        Err(err) => {
+            error!("can't generate the map: {}", err);
+            *layout =
+                MapLayout::with_ground(config.map.size, config.map.tile_size, |_| Biome::Grass);
+            next_state.set(GameState::MainMenu);
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct GroundTile {
+    pub biome: Biome,
+}
+
+/// Spawns the tiles of the ground from the layout
+pub fn spawn_ground_tiles(commands: &mut Commands, layout: &MapLayout, config: &GameConfig) {
+    for y in 0..layout.tiles_per_side {
+        for x in 0..layout.tiles_per_side {
+            let biome = layout.tiles[y * layout.tiles_per_side + x];
+            commands.spawn((
+                SpriteBundle {
+                    transform: Transform::from_translation(
+                        layout.tile_center(x, y).extend(config.map.ground_z),
+                    ),
+                    sprite: Sprite {
+                        color: biome.config(config).color,
+                        custom_size: Some(Vec2::splat(layout.tile_size)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                GroundTile { biome },
+                StateScoped(InRound),
+            ));
+        }
+    }
+}
+
+pub fn spawn_ground(mut commands: Commands, layout: Res<MapLayout>, config: Res<GameConfig>) {
+    spawn_ground_tiles(&mut commands, &layout, &config);
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+pub enum ObstacleKind {
+    Tree,
+    Rock,
+    Bush,
+}
+
+impl ObstacleKind {
+    /// Nobody can go through the trees and the rocks
+    pub fn is_blocking(&self) -> bool {
+        match self {
+            ObstacleKind::Tree | ObstacleKind::Rock => true,
+            ObstacleKind::Bush => false,
+        }
+    }
+
+    pub fn config<'a>(&self, config: &'a GameConfig) -> &'a ObstacleConfig {
+        match self {
+            ObstacleKind::Tree => &config.map.trees,
+            ObstacleKind::Rock => &config.map.rocks,
+            ObstacleKind::Bush => &config.map.bushes,
+        }
+    }
+}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Obstacle {
+    pub kind: ObstacleKind,
+    pub radius: f32,
+}
+
+/// Spawns the round obstacle of its kind
+pub fn spawn_obstacle(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    material: &mut Assets<ColorMaterial>,
+    config: &GameConfig,
+    obstacle: Obstacle,
+    pos: Vec2,
+) -> Entity {
+    commands
+        .spawn((
+            MaterialMesh2dBundle {
+                mesh: Mesh2dHandle(meshes.add(Circle::new(obstacle.radius))),
+                material: material.add(obstacle.kind.config(config).color),
+                transform: Transform::from_translation(pos.extend(config.map.obstacle_z)),
+                ..Default::default()
+            },
+            obstacle,
+            StateScoped(InRound),
+        ))
+        .id()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_obstacles(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut material: ResMut<Assets<ColorMaterial>>,
+    player_q: Query<&Transform, With<Player>>,
+    bases_q: Query<&Transform, With<Base>>,
+    werewolves_q: Query<&Transform, With<Werewolf>>,
+    corrals_q: Query<(&Transform, &ChickenCorral)>,
+    layout: Res<MapLayout>,
+    mut rng: ResMut<GameRng>,
+    config: Res<GameConfig>,
+) {
+    let map = &config.map;
+
+    // the places, which must stay free, as circles (center and radius)
+    let mut taken: Vec<(Vec2, f32)> = Vec::new();
+    taken.extend(
+        player_q
+            .iter()
+            .map(|pos| (pos.translation.xy(), config.player.size / 2.)),
+    );
+    // the player must be able to come to the base
+    taken.extend(
+        bases_q
+            .iter()
+            .map(|pos| (pos.translation.xy(), config.base.catching_radius)),
+    );
+    taken.extend(
+        werewolves_q
+            .iter()
+            .map(|pos| (pos.translation.xy(), config.werewolf.size / 2.)),
+    );
+    taken.extend(corrals_q.iter().map(|(pos, corral)| {
+        (
+            pos.translation.xy(),
+            corral_footprint(&config, corral.length, corral.heigth),
+        )
+    }));
+
+    for place in layout.obstacles.iter() {
+        spawn_obstacle(
+            &mut commands,
+            &mut meshes,
+            &mut material,
+            &config,
+            place.obstacle.clone(),
+            place.pos,
+        );
+        taken.push((place.pos, place.obstacle.radius));
+    }
+    if !layout.random_obstacles {
+        return;
+    }
+
+    for kind in [ObstacleKind::Tree, ObstacleKind::Rock, ObstacleKind::Bush] {
+        let obstacle_config = kind.config(&config);
+
+        for _ in 0..obstacle_config.amount {
+            let radius = obstacle_config.radius * rng.gen_range(0.75..1.25);
+
+            for _ in 0..MAX_PLACING_TRIES {
+                let half_extent = layout.half_extent(radius * 2.);
+                let pos = Vec2::new(
+                    rng.gen_range(-half_extent..=half_extent),
+                    rng.gen_range(-half_extent..=half_extent),
+                );
+
+                let is_free = taken.iter().all(|(taken_pos, taken_radius)| {
+                    pos.distance(*taken_pos) > radius + taken_radius + map.clearance
+                });
+                if is_free {
+                    spawn_obstacle(
+                        &mut commands,
+                        &mut meshes,
+                        &mut material,
+                        &config,
+                        Obstacle { kind, radius },
+                        pos,
+                    );
+                    taken.push((pos, radius));
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn mover_size(is_player: bool, is_chicken: bool, config: &GameConfig) -> f32 {
+    if is_player {
+        config.player.size
+    } else if is_chicken {
+        config.chicken.size
+    } else {
+        config.werewolf.size
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn keep_in_arena(
+    mut movers_q: Query<
+        (&mut Transform, Has<Player>, Option<&mut Chicken>),
+        Or<(With<Player>, With<Chicken>, With<Werewolf>)>,
+    >,
+    layout: Res<MapLayout>,
+    config: Res<GameConfig>,
+) {
+    for (mut m_pos, is_player, mut chicken) in movers_q.iter_mut() {
+        let half_extent = layout.half_extent(mover_size(is_player, chicken.is_some(), &config));
+        let pos = m_pos.translation.xy();
+        let clamped = pos.clamp(Vec2::splat(-half_extent), Vec2::splat(half_extent));
+        if clamped == pos {
+            continue;
+        }
+        m_pos.translation = clamped.extend(m_pos.translation.z);
+
+        // the chicken is turning away from the edge, so it is not walking into it forever
+        if let Some(chicken) = chicken.as_mut() {
+            chicken.bounce_off_edge(clamped.x != pos.x, clamped.y != pos.y);
+        }
+    }
+}
+
+/// The werewolf in the bush can be seen only by the player near to it
+pub fn is_hidden_in_bush(
+    w_pos: Vec2,
+    player_pos: Option<Vec2>,
+    obstacles_q: &Query<(&Transform, &Obstacle)>,
+    config: &GameConfig,
+) -> bool {
+    let in_bush = obstacles_q.iter().any(|(o_pos, obstacle)| {
+        obstacle.kind == ObstacleKind::Bush
+            && w_pos.distance(o_pos.translation.xy()) < obstacle.radius
+    });
+    let player_near =
+        player_pos.is_some_and(|p_pos| p_pos.distance(w_pos) <= config.map.bush_reveal_distance);
+
+    in_bush && !player_near
+}
+
+/// Without the fog, only the bushes are hiding the werewolves (with the fog see
+/// `hide_out_of_sight`)
+pub fn hide_werewolves_in_bushes(
+    mut werewolves_q: Query<(&Transform, &mut Visibility), With<Werewolf>>,
+    obstacles_q: Query<(&Transform, &Obstacle)>,
+    player_q: Query<&Transform, With<Player>>,
+    config: Res<GameConfig>,
+) {
+    let player_pos = player_q.get_single().ok().map(|pos| pos.translation.xy());
+
+    for (w_pos, mut visibility) in werewolves_q.iter_mut() {
+        let hidden = is_hidden_in_bush(w_pos.translation.xy(), player_pos, &obstacles_q, &config);
+        let next_visibility = if hidden {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+        // the change detection is not triggered every frame
+        visibility.set_if_neq(next_visibility);
+    }
+}
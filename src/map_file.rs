@@ -0,0 +1,347 @@
+use std::{fmt::Display, fs, path::Path};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::GameConfig,
+    map::{
+        Biome, CorralOwner, CorralPlace, MapLayout, Obstacle, ObstacleKind, ObstaclePlace,
+        WerewolfPlaces,
+    },
+};
+
+/// The hand made level, which is loaded instead of the generated map, when the `file` is set in
+/// the map section of the config (or with `--map path`).
+///
+/// Everything is placed exactly like in the file, there is an example in maps/tutorial.ron. The
+/// positions are in the world coordinates, the center of the arena is (0, 0)
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MapFile {
+    /// the side length of the arena, without it the size from the config is taken
+    #[serde(default)]
+    pub size: Option<f32>,
+    /// the ground everywhere, where is no biome area
+    #[serde(default)]
+    pub biome: Biome,
+    /// the later areas are drawn over the earlier ones
+    #[serde(default)]
+    pub biome_areas: Vec<BiomeArea>,
+    pub player: Vec2,
+    pub player_base: Vec2,
+    #[serde(default)]
+    pub werewolves: Vec<WerewolfPlaces>,
+    /// every werewolf must have exactly one corral, the player can have one
+    #[serde(default)]
+    pub corrals: Vec<CorralPlace>,
+    #[serde(default)]
+    pub obstacles: Vec<MapObstacle>,
+    /// if the obstacles from the config are placed randomly after the ones from the file
+    #[serde(default)]
+    pub random_obstacles: bool,
+}
+
+/// The rectangle on the ground with its own biome
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BiomeArea {
+    pub biome: Biome,
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MapObstacle {
+    pub kind: ObstacleKind,
+    pub pos: Vec2,
+    /// without it the radius from the config is taken
+    #[serde(default)]
+    pub radius: Option<f32>,
+}
+
+#[derive(Debug)]
+pub enum MapError {
+    Io(std::io::Error),
+    Serialize(ron::Error),
+    Parse(ron::error::SpannedError),
+    Invalid(String),
+}
+
+impl Display for MapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MapError::Io(err) => write!(f, "can't read the map file: {}", err),
+            MapError::Serialize(err) => write!(f, "can't write the map: {}", err),
+            MapError::Parse(err) => write!(f, "can't parse the map file: {}", err),
+            MapError::Invalid(reason) => write!(f, "invalid map: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for MapError {}
+
+impl From<std::io::Error> for MapError {
+    fn from(err: std::io::Error) -> Self {
+        MapError::Io(err)
+    }
+}
+
+impl From<ron::Error> for MapError {
+    fn from(err: ron::Error) -> Self {
+        MapError::Serialize(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for MapError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        MapError::Parse(err)
+    }
+}
+
+impl MapFile {
+    /// The map file with the same places as in the layout, the ground is left out
+    pub fn from_layout(layout: &MapLayout) -> Self {
+        Self {
+            size: Some(layout.size),
+            biome: Biome::default(),
+            biome_areas: Vec::new(),
+            player: layout.player,
+            player_base: layout.player_base,
+            werewolves: layout.werewolves.clone(),
+            corrals: layout.corrals.clone(),
+            obstacles: layout
+                .obstacles
+                .iter()
+                .map(|place| MapObstacle {
+                    kind: place.obstacle.kind,
+                    pos: place.pos,
+                    radius: Some(place.obstacle.radius),
+                })
+                .collect(),
+            random_obstacles: layout.random_obstacles,
+        }
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), MapError> {
+        let map = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, map)?;
+
+        Ok(())
+    }
+
+    /// Reads the map from the RON file and checks it
+    pub fn load(path: impl AsRef<Path>, config: &GameConfig) -> Result<Self, MapError> {
+        let map_file: MapFile = ron::from_str(&fs::read_to_string(path)?)?;
+        map_file.validate(config)?;
+
+        Ok(map_file)
+    }
+
+    pub fn size(&self, config: &GameConfig) -> f32 {
+        self.size.unwrap_or(config.map.size)
+    }
+
+    /// Checks the things, which would break the spawning or the behaviour of the entities
+    pub fn validate(&self, config: &GameConfig) -> Result<(), MapError> {
+        let size = self.size(config);
+        if size <= 0. {
+            return Err(MapError::Invalid(format!(
+                "size ({}) must be bigger than 0",
+                size
+            )));
+        }
+
+        let mut places = vec![("player", self.player), ("player base", self.player_base)];
+        for werewolf in self.werewolves.iter() {
+            places.push(("werewolf", werewolf.werewolf));
+            places.push(("werewolf base", werewolf.base));
+        }
+        places.extend(self.corrals.iter().map(|corral| ("corral", corral.pos)));
+        places.extend(
+            self.obstacles
+                .iter()
+                .map(|obstacle| ("obstacle", obstacle.pos)),
+        );
+        for (name, pos) in places {
+            if pos.x.abs() > size / 2. || pos.y.abs() > size / 2. {
+                return Err(MapError::Invalid(format!(
+                    "the {} {} is outside of the arena with size {}",
+                    name, pos, size
+                )));
+            }
+        }
+
+        for corral in self.corrals.iter() {
+            // the chickens are spawned at least one wall away from the walls of the corral
+            if corral.length.min(corral.heigth) <= 2 {
+                return Err(MapError::Invalid(format!(
+                    "the corral {} must be bigger than 2 walls, but it is {}x{}",
+                    corral.pos, corral.length, corral.heigth
+                )));
+            }
+            if let CorralOwner::Werewolf(i) = corral.owner {
+                if i >= self.werewolves.len() {
+                    return Err(MapError::Invalid(format!(
+                        "the corral {} belongs to the werewolf {}, but there are only {} werewolves",
+                        corral.pos,
+                        i,
+                        self.werewolves.len()
+                    )));
+                }
+            }
+        }
+
+        let corrals_of = |owner| self.corrals.iter().filter(|c| c.owner == owner).count();
+        for i in 0..self.werewolves.len() {
+            let amount = corrals_of(CorralOwner::Werewolf(i));
+            if amount != 1 {
+                return Err(MapError::Invalid(format!(
+                    "the werewolf {} must have exactly one corral, but it has {}",
+                    i, amount
+                )));
+            }
+        }
+        if corrals_of(CorralOwner::Player) > 1 {
+            return Err(MapError::Invalid(
+                "the player can have only one corral".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn to_layout(&self, config: &GameConfig) -> MapLayout {
+        let mut layout = MapLayout::with_ground(self.size(config), config.map.tile_size, |pos| {
+            self.biome_areas
+                .iter()
+                .rev()
+                .find(|area| pos.cmpge(area.min).all() && pos.cmple(area.max).all())
+                .map_or(self.biome, |area| area.biome)
+        });
+
+        layout.player = self.player;
+        layout.player_base = self.player_base;
+        layout.werewolves = self.werewolves.clone();
+        layout.corrals = self.corrals.clone();
+        layout.obstacles = self
+            .obstacles
+            .iter()
+            .map(|obstacle| ObstaclePlace {
+                pos: obstacle.pos,
+                obstacle: Obstacle {
+                    kind: obstacle.kind,
+                    radius: obstacle
+                        .radius
+                        .unwrap_or(obstacle.kind.config(config).radius),
+                },
+            })
+            .collect();
+        layout.random_obstacles = self.random_obstacles;
+
+        layout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The player with the corral and one werewolf with its corral
+    fn map_file() -> MapFile {
+        let corral = |pos: Vec2, owner| CorralPlace {
+            pos,
+            length: 5,
+            heigth: 5,
+            owner,
+            flocking: None,
+        };
+        MapFile {
+            size: Some(2000.),
+            biome: Biome::default(),
+            biome_areas: Vec::new(),
+            player: Vec2::ZERO,
+            player_base: Vec2::new(0., 100.),
+            werewolves: vec![WerewolfPlaces {
+                werewolf: Vec2::new(500., 0.),
+                base: Vec2::new(600., 0.),
+            }],
+            corrals: vec![
+                corral(Vec2::new(-400., 0.), CorralOwner::Player),
+                corral(Vec2::new(400., 400.), CorralOwner::Werewolf(0)),
+            ],
+            obstacles: Vec::new(),
+            random_obstacles: false,
+        }
+    }
+
+    fn assert_invalid(map_file: MapFile) {
+        let config = GameConfig::default();
+        assert!(matches!(
+            map_file.validate(&config),
+            Err(MapError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn valid_map_file() {
+        map_file().validate(&GameConfig::default()).unwrap();
+    }
+
+    #[test]
+    fn tutorial_is_valid() {
+        MapFile::load("maps/tutorial.ron", &GameConfig::default()).unwrap();
+    }
+
+    #[test]
+    fn places_must_be_in_the_arena() {
+        let mut outside_player = map_file();
+        outside_player.player = Vec2::new(1001., 0.);
+        assert_invalid(outside_player);
+
+        let mut outside_obstacle = map_file();
+        outside_obstacle.obstacles.push(MapObstacle {
+            kind: ObstacleKind::Rock,
+            pos: Vec2::new(0., -1500.),
+            radius: None,
+        });
+        assert_invalid(outside_obstacle);
+    }
+
+    #[test]
+    fn corral_must_be_bigger_than_two_walls() {
+        let mut map_file = map_file();
+        map_file.corrals[0].length = 2;
+        assert_invalid(map_file);
+    }
+
+    #[test]
+    fn werewolf_must_have_exactly_one_corral() {
+        let mut without_corral = map_file();
+        without_corral.corrals.pop();
+        assert_invalid(without_corral);
+
+        let mut two_corrals = map_file();
+        let mut corral = two_corrals.corrals[1].clone();
+        corral.pos = Vec2::new(400., -400.);
+        two_corrals.corrals.push(corral);
+        assert_invalid(two_corrals);
+    }
+
+    #[test]
+    fn corral_of_missing_werewolf() {
+        let mut map_file = map_file();
+        map_file.corrals[1].owner = CorralOwner::Werewolf(1);
+        assert_invalid(map_file);
+    }
+
+    #[test]
+    fn player_can_have_only_one_corral() {
+        let mut map_file = map_file();
+        let mut corral = map_file.corrals[0].clone();
+        corral.pos = Vec2::new(-400., -400.);
+        map_file.corrals.push(corral);
+        assert_invalid(map_file);
+    }
+}
@@ -1,5 +1,296 @@
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    render::{camera::Viewport, view::RenderLayers},
+    window::PrimaryWindow,
+};
+
+use crate::{
+    base::{Base, BaseText, BelongToBase},
+    camera::{move_camera, MainCamera},
+    chicken_corral::ChickenCorral,
+    config::GameConfig,
+    fog::{in_sight, FogTile},
+    map::{GroundTile, MapLayout},
+    player::{ForPlayer, Player},
+    state::{GameState, InRound},
+    werewolf::Werewolf,
+};
+
+/// Only the minimap camera is rendering this layer, the main camera is rendering the layer 0
+pub const MINIMAP_LAYER: usize = 1;
+
+/// The overview of the whole arena in the corner of the window.
+///
+/// The minimap camera is rendering only the ground and the icons, which are added as children to
+/// the player, bases, corrals and werewolves. Holding the mouse on the minimap moves the main
+/// camera there, after releasing it the camera is going back to the player.
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>();
+        app.init_resource::<MapLayout>();
+
+        app.add_systems(Startup, spawn_minimap_camera);
+        app.add_systems(OnEnter(InRound), show_minimap);
+        app.add_systems(OnExit(InRound), hide_minimap);
+        app.add_systems(
+            Update,
+            (update_minimap_viewport, add_minimap_icons).run_if(in_state(InRound)),
+        );
+        app.add_systems(
+            Update,
+            (
+                toggle_minimap,
+                show_werewolves_in_sight,
+                // the main camera is following the player, so the panning must be after it
+                pan_camera_from_minimap.after(move_camera),
+            )
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
 
 // see viewport in camera settings
 #[derive(Component)]
 pub struct MinimapCamera;
+
+#[derive(Component)]
+pub struct MinimapIcon;
+
+#[derive(Component)]
+pub struct WerewolfMinimapIcon;
+
+pub fn spawn_minimap_camera(mut commands: Commands, config: Res<GameConfig>) {
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                // over the main camera
+                order: 1,
+                is_active: false,
+                clear_color: ClearColorConfig::Custom(config.minimap.background_color),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        RenderLayers::layer(MINIMAP_LAYER),
+        MinimapCamera,
+    ));
+}
+
+pub fn show_minimap(
+    mut camera_q: Query<&mut Camera, With<MinimapCamera>>,
+    config: Res<GameConfig>,
+) {
+    for mut camera in camera_q.iter_mut() {
+        camera.is_active = config.minimap.shown;
+    }
+}
+
+pub fn hide_minimap(mut camera_q: Query<&mut Camera, With<MinimapCamera>>) {
+    for mut camera in camera_q.iter_mut() {
+        camera.is_active = false;
+    }
+}
+
+pub fn toggle_minimap(
+    mut camera_q: Query<&mut Camera, With<MinimapCamera>>,
+    input: Res<ButtonInput<KeyCode>>,
+    config: Res<GameConfig>,
+) {
+    if input.just_pressed(config.minimap.key_toggle) {
+        for mut camera in camera_q.iter_mut() {
+            camera.is_active = !camera.is_active;
+        }
+    }
+}
+
+// the window can be resized and the arena of the loaded map can have another size
+pub fn update_minimap_viewport(
+    mut camera_q: Query<(&mut Camera, &mut OrthographicProjection), With<MinimapCamera>>,
+    window_q: Query<&Window, With<PrimaryWindow>>,
+    layout: Res<MapLayout>,
+    config: Res<GameConfig>,
+) {
+    let (Ok((mut camera, mut projection)), Ok(window)) =
+        (camera_q.get_single_mut(), window_q.get_single())
+    else {
+        return;
+    };
+
+    let size = (config.minimap.size * window.scale_factor()) as u32;
+    let margin = (config.minimap.margin * window.scale_factor()) as u32;
+    let window_size = UVec2::new(window.physical_width(), window.physical_height());
+    // bottom right corner, smaller windows are showing at least a part of it
+    let viewport = Viewport {
+        physical_position: window_size.saturating_sub(UVec2::splat(size + margin)),
+        physical_size: UVec2::splat(size).min(window_size).max(UVec2::ONE),
+        ..Default::default()
+    };
+    if camera
+        .viewport
+        .as_ref()
+        .map(|v| (v.physical_position, v.physical_size))
+        != Some((viewport.physical_position, viewport.physical_size))
+    {
+        camera.viewport = Some(viewport);
+    }
+
+    let scale = layout.size / config.minimap.size;
+    if projection.scale != scale {
+        projection.scale = scale;
+    }
+}
+
+fn icon(color: Color, size: Vec2) -> (SpriteBundle, RenderLayers, MinimapIcon) {
+    (
+        SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(size),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        RenderLayers::layer(MINIMAP_LAYER),
+        MinimapIcon,
+    )
+}
+
+/// Adds the icons to the new entities, also to the ones from the loaded game
+pub fn add_minimap_icons(
+    mut commands: Commands,
+    player_q: Query<Entity, Added<Player>>,
+    base_q: Query<(Entity, Has<ForPlayer>), Added<Base>>,
+    corral_q: Query<(Entity, &ChickenCorral, Has<ForPlayer>), Added<ChickenCorral>>,
+    werewolf_q: Query<Entity, Added<Werewolf>>,
+    ground_q: Query<Entity, Or<(Added<GroundTile>, Added<FogTile>)>>,
+    config: Res<GameConfig>,
+) {
+    let minimap = &config.minimap;
+
+    // the ground and the fog are on the both cameras
+    for tile_ent in ground_q.iter() {
+        commands
+            .entity(tile_ent)
+            .insert(RenderLayers::from_layers(&[0, MINIMAP_LAYER]));
+    }
+
+    for (corral_ent, corral, for_player) in corral_q.iter() {
+        let color = if for_player {
+            minimap.player_color
+        } else {
+            config.corral.wall_color
+        };
+        let size =
+            Vec2::new(corral.length as f32, corral.heigth as f32) * config.corral.wall_length;
+        commands.entity(corral_ent).with_children(|parent| {
+            parent.spawn(icon(color.with_alpha(0.5), size));
+        });
+    }
+
+    for (base_ent, for_player) in base_q.iter() {
+        let color = if for_player {
+            minimap.player_color
+        } else {
+            config.base.color
+        };
+        commands.entity(base_ent).with_children(|parent| {
+            parent.spawn(icon(color, Vec2::splat(minimap.icon_size)));
+            // the text is updated together with the text on the base
+            parent.spawn((
+                BaseText {
+                    base: BelongToBase { base: base_ent },
+                    text_bundle: Text2dBundle {
+                        transform: Transform::from_xyz(0., minimap.icon_size, config.text_z),
+                        text: Text::from_section(
+                            "0",
+                            TextStyle {
+                                font_size: minimap.text_size,
+                                ..Default::default()
+                            },
+                        ),
+                        ..Default::default()
+                    },
+                },
+                RenderLayers::layer(MINIMAP_LAYER),
+                MinimapIcon,
+            ));
+        });
+    }
+
+    for werewolf_ent in werewolf_q.iter() {
+        commands.entity(werewolf_ent).with_children(|parent| {
+            parent.spawn((
+                icon(config.werewolf.color, Vec2::splat(minimap.icon_size)),
+                WerewolfMinimapIcon,
+            ));
+        });
+    }
+
+    for player_ent in player_q.iter() {
+        commands.entity(player_ent).with_children(|parent| {
+            parent.spawn(icon(minimap.player_color, Vec2::splat(minimap.icon_size)));
+        });
+    }
+}
+
+// the werewolves in the bushes are hidden with their icons, because the icons are inheriting the
+// visibility
+pub fn show_werewolves_in_sight(
+    mut icons_q: Query<(&Parent, &mut Visibility), With<WerewolfMinimapIcon>>,
+    werewolves_q: Query<&Transform, With<Werewolf>>,
+    player_q: Query<(&Transform, &Player)>,
+    config: Res<GameConfig>,
+) {
+    let Ok((p_pos, player)) = player_q.get_single() else {
+        return;
+    };
+
+    for (parent, mut visibility) in icons_q.iter_mut() {
+        let Ok(w_pos) = werewolves_q.get(parent.get()) else {
+            continue;
+        };
+        let in_sight = in_sight(
+            player,
+            p_pos.translation.xy(),
+            w_pos.translation.xy(),
+            &config,
+        );
+
+        visibility.set_if_neq(if in_sight {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+}
+
+pub fn pan_camera_from_minimap(
+    minimap_q: Query<(&Camera, &GlobalTransform), With<MinimapCamera>>,
+    mut camera_q: Query<&mut Transform, With<MainCamera>>,
+    window_q: Query<&Window, With<PrimaryWindow>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+) {
+    if !mouse.pressed(MouseButton::Left) {
+        return;
+    }
+    let (Ok((minimap, m_transform)), Ok(window), Ok(mut c_pos)) = (
+        minimap_q.get_single(),
+        window_q.get_single(),
+        camera_q.get_single_mut(),
+    ) else {
+        return;
+    };
+    let (Some(cursor), Some(rect)) = (window.cursor_position(), minimap.logical_viewport_rect())
+    else {
+        return;
+    };
+    if !minimap.is_active || !rect.contains(cursor) {
+        return;
+    }
+
+    if let Some(pos) = minimap.viewport_to_world_2d(m_transform, cursor - rect.min) {
+        c_pos.translation = pos.extend(c_pos.translation.z);
+    }
+}
@@ -0,0 +1,547 @@
+use bevy::prelude::*;
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, VecDeque},
+};
+
+use crate::{
+    chicken_corral::ChickenCorralWall,
+    collision::{Collider, CollisionLayer, Shape},
+    config::GameConfig,
+    map::{MapLayout, Obstacle},
+    state::InRound,
+};
+
+/// The werewolves are finding their way around the corral walls and the obstacles on a grid.
+///
+/// The grid is covering the whole arena, the cell is blocked, when the werewolf standing in its
+/// center would touch a wall or an obstacle. It is built again every time, when the walls or the
+/// obstacles are changed (the new round, the loaded game, the config).
+pub struct NavigationPlugin;
+
+impl Plugin for NavigationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>();
+        app.init_resource::<MapLayout>();
+        app.init_resource::<NavGrid>();
+
+        app.add_systems(Update, build_nav_grid.run_if(in_state(InRound)));
+    }
+}
+
+// the costs are in the tenths of the cell, so they are the same on every machine
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+/// how far the free cell is searched for the start or the goal in the blocked cell
+const MAX_FREE_CELL_DISTANCE: i32 = 10;
+
+#[derive(Resource, Default)]
+pub struct NavGrid {
+    cell_size: f32,
+    cells_per_side: i32,
+    /// the bottom left corner of the arena
+    origin: Vec2,
+    blocked: Vec<bool>,
+}
+
+impl NavGrid {
+    /// The movers with the `clearance` radius can't stand in the cells near the colliders
+    pub fn new(
+        layout: &MapLayout,
+        cell_size: f32,
+        clearance: f32,
+        colliders: impl Iterator<Item = (Vec2, Shape)>,
+    ) -> Self {
+        let cells_per_side = (layout.size / cell_size).ceil() as i32;
+        let mut grid = Self {
+            cell_size,
+            cells_per_side,
+            origin: Vec2::splat(-layout.size / 2.),
+            blocked: vec![false; (cells_per_side * cells_per_side) as usize],
+        };
+
+        for (pos, shape) in colliders {
+            let extent = match shape {
+                Shape::Circle { radius } => Vec2::splat(radius),
+                Shape::Rect { half_size } => half_size,
+            } + clearance;
+            let min = grid.cell_of_clamped(pos - extent);
+            let max = grid.cell_of_clamped(pos + extent);
+
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let offset = grid.center_of(IVec2::new(x, y)) - pos;
+                    let distance = match shape {
+                        Shape::Circle { radius } => offset.length() - radius,
+                        Shape::Rect { half_size } => {
+                            (offset.abs() - half_size).max(Vec2::ZERO).length()
+                        }
+                    };
+                    if distance < clearance {
+                        let i = grid.index(IVec2::new(x, y));
+                        grid.blocked[i] = true;
+                    }
+                }
+            }
+        }
+        grid
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    fn contains(&self, cell: IVec2) -> bool {
+        cell.cmpge(IVec2::ZERO).all() && cell.cmplt(IVec2::splat(self.cells_per_side)).all()
+    }
+
+    fn index(&self, cell: IVec2) -> usize {
+        (cell.y * self.cells_per_side + cell.x) as usize
+    }
+
+    fn cell_at(&self, i: usize) -> IVec2 {
+        IVec2::new(
+            i as i32 % self.cells_per_side,
+            i as i32 / self.cells_per_side,
+        )
+    }
+
+    /// None outside of the arena
+    pub fn cell_of(&self, pos: Vec2) -> Option<IVec2> {
+        let cell = ((pos - self.origin) / self.cell_size).floor().as_ivec2();
+        self.contains(cell).then_some(cell)
+    }
+
+    fn cell_of_clamped(&self, pos: Vec2) -> IVec2 {
+        ((pos - self.origin) / self.cell_size)
+            .floor()
+            .as_ivec2()
+            .clamp(IVec2::ZERO, IVec2::splat(self.cells_per_side - 1))
+    }
+
+    fn center_of(&self, cell: IVec2) -> Vec2 {
+        self.origin + (cell.as_vec2() + 0.5) * self.cell_size
+    }
+
+    fn is_free(&self, cell: IVec2, avoid: &[IVec2]) -> bool {
+        self.contains(cell) && !self.blocked[self.index(cell)] && !avoid.contains(&cell)
+    }
+
+    /// The free cell nearest to this one, the movers pushed into the walls are starting from it
+    fn nearest_free(&self, cell: IVec2, avoid: &[IVec2]) -> Option<IVec2> {
+        if self.is_free(cell, avoid) {
+            return Some(cell);
+        }
+
+        // the rings around the cell, the nearest cell in the first ring with a free one is taken
+        (1..=MAX_FREE_CELL_DISTANCE).find_map(|distance| {
+            (-distance..=distance)
+                .flat_map(|y| (-distance..=distance).map(move |x| IVec2::new(x, y)))
+                .filter(|offset| offset.x.abs() == distance || offset.y.abs() == distance)
+                .map(|offset| cell + offset)
+                .filter(|ring_cell| self.is_free(*ring_cell, avoid))
+                .min_by_key(|ring_cell| (*ring_cell - cell).length_squared())
+        })
+    }
+
+    /// The mover is not touching anything on the straight way between the points. Every cell
+    /// under the line is checked, when the line goes exactly through the corner, also both cells
+    /// next to it
+    fn line_of_sight(&self, a: Vec2, b: Vec2, avoid: &[IVec2]) -> bool {
+        let (Some(mut cell), Some(end)) = (self.cell_of(a), self.cell_of(b)) else {
+            return false;
+        };
+
+        // the way through the grid in the cells, the next border in every axis is crossed at t
+        let from = (a - self.origin) / self.cell_size;
+        let dir = (b - self.origin) / self.cell_size - from;
+        let sign = |v: f32| (v > 0.) as i32 - (v < 0.) as i32;
+        let step = IVec2::new(sign(dir.x), sign(dir.y));
+        let next_border = |cell: i32, step: i32, from: f32, dir: f32| match step {
+            1 => (cell as f32 + 1. - from) / dir,
+            -1 => (cell as f32 - from) / dir,
+            _ => f32::INFINITY,
+        };
+        let mut t_max = Vec2::new(
+            next_border(cell.x, step.x, from.x, dir.x),
+            next_border(cell.y, step.y, from.y, dir.y),
+        );
+        let t_delta = (1. / dir).abs();
+
+        // every step is going to the neighbour cell, so the end is reached after these steps
+        let steps = (end - cell).abs().element_sum();
+        for _ in 0..=steps {
+            if !self.is_free(cell, avoid) {
+                return false;
+            }
+            if cell == end {
+                return true;
+            }
+
+            if t_max.x < t_max.y {
+                cell.x += step.x;
+                t_max.x += t_delta.x;
+            } else if t_max.y < t_max.x {
+                cell.y += step.y;
+                t_max.y += t_delta.y;
+            } else {
+                if !self.is_free(cell + IVec2::new(step.x, 0), avoid)
+                    || !self.is_free(cell + IVec2::new(0, step.y), avoid)
+                {
+                    return false;
+                }
+                cell += step;
+                t_max += t_delta;
+            }
+        }
+
+        // missed the end because of the rounding, it is safer to take the way around
+        false
+    }
+
+    /// The A* on the grid, the cells in `avoid` are taken as blocked. The path is going through
+    /// the cells without cutting the corners, then the waypoints, which can be skipped by going
+    /// straight, are left out. The last waypoint is always the goal.
+    ///
+    /// Returns None, when the goal can't be reached (or there is no grid yet)
+    pub fn find_path(&self, from: Vec2, to: Vec2, avoid: &[IVec2]) -> Option<Vec<Vec2>> {
+        let start = self.nearest_free(self.cell_of(from)?, avoid)?;
+        let goal_cell = self.cell_of(to)?;
+        let goal = self.nearest_free(goal_cell, avoid)?;
+
+        let heuristic = |cell: IVec2| {
+            let diff = (goal - cell).abs();
+            STRAIGHT_COST * diff.max_element() as u32
+                + (DIAGONAL_COST - STRAIGHT_COST) * diff.min_element() as u32
+        };
+        let (start_i, goal_i) = (self.index(start), self.index(goal));
+        let mut cost = vec![u32::MAX; self.blocked.len()];
+        let mut came_from = vec![usize::MAX; self.blocked.len()];
+        let mut closed = vec![false; self.blocked.len()];
+        // the index is deciding between the cells with the same cost, so the path is always the
+        // same
+        let mut open = BinaryHeap::new();
+        cost[start_i] = 0;
+        open.push(Reverse((heuristic(start), start_i)));
+
+        while let Some(Reverse((_, i))) = open.pop() {
+            if i == goal_i {
+                break;
+            }
+            if closed[i] {
+                continue;
+            }
+            closed[i] = true;
+
+            let cell = self.cell_at(i);
+            for y in -1..=1 {
+                for x in -1..=1 {
+                    let dir = IVec2::new(x, y);
+                    let next = cell + dir;
+                    if dir == IVec2::ZERO || !self.is_free(next, avoid) {
+                        continue;
+                    }
+                    let diagonal = x != 0 && y != 0;
+                    if diagonal
+                        && !(self.is_free(cell + IVec2::new(x, 0), avoid)
+                            && self.is_free(cell + IVec2::new(0, y), avoid))
+                    {
+                        continue;
+                    }
+
+                    let next_i = self.index(next);
+                    let next_cost = cost[i]
+                        + if diagonal {
+                            DIAGONAL_COST
+                        } else {
+                            STRAIGHT_COST
+                        };
+                    if next_cost < cost[next_i] {
+                        cost[next_i] = next_cost;
+                        came_from[next_i] = i;
+                        open.push(Reverse((next_cost + heuristic(next), next_i)));
+                    }
+                }
+            }
+        }
+
+        if cost[goal_i] == u32::MAX {
+            return None;
+        }
+
+        // the goal in the blocked cell is reached straight from the nearest free one, the mover is
+        // stopped by the collisions as near to it as possible
+        let mut points = vec![to];
+        if goal != goal_cell {
+            points.push(self.center_of(goal));
+        }
+        let mut i = goal_i;
+        while i != start_i {
+            i = came_from[i];
+            points.push(self.center_of(self.cell_at(i)));
+        }
+        points.reverse();
+
+        // the mover is going straight as far as it can see, then it is turning, so every point is
+        // checked only once or twice
+        let mut waypoints = Vec::new();
+        let mut current = from;
+        let mut k = 0;
+        while k < points.len() {
+            let mut farthest = k;
+            while farthest + 1 < points.len()
+                && self.line_of_sight(current, points[farthest + 1], avoid)
+            {
+                farthest += 1;
+            }
+            waypoints.push(points[farthest]);
+            current = points[farthest];
+            k = farthest + 1;
+        }
+        Some(waypoints)
+    }
+}
+
+/// The way of the mover to its goal, it is planned again, when the goal was moved or the mover
+/// is stuck
+#[derive(Default, Clone, Debug)]
+pub struct NavPath {
+    goal: Option<Vec2>,
+    waypoints: VecDeque<Vec2>,
+    /// the cells, where the mover was stuck on the way to this goal
+    avoid: Vec<IVec2>,
+    last_pos: Vec2,
+    stuck_time: f32,
+}
+
+impl NavPath {
+    /// Returns the point, to which the mover should go straight now. The mover is stuck, when it
+    /// moved less than `min_step` in every frame for the `replan_delay` seconds.
+    ///
+    /// Without the path to the goal it is going straight to it
+    pub fn next_point(
+        &mut self,
+        pos: Vec2,
+        goal: Vec2,
+        min_step: f32,
+        delta: f32,
+        grid: &NavGrid,
+        config: &GameConfig,
+    ) -> Vec2 {
+        let goal_moved = match self.goal {
+            Some(old_goal) => old_goal.distance(goal) > grid.cell_size(),
+            None => true,
+        };
+        if goal_moved {
+            self.avoid.clear();
+            self.stuck_time = 0.;
+        } else if pos.distance(self.last_pos) < min_step {
+            self.stuck_time += delta;
+        } else {
+            self.stuck_time = 0.;
+        }
+        self.last_pos = pos;
+
+        let stuck = self.stuck_time >= config.werewolf.replan_delay;
+        if stuck {
+            // something is in the way, which is not in the grid (like the base or the other
+            // werewolf), so the cell in front of the mover is avoided in the new path
+            if let Some(cell) = self.waypoints.front().and_then(|next| grid.cell_of(*next)) {
+                if !self.avoid.contains(&cell) {
+                    self.avoid.push(cell);
+                }
+            }
+            self.stuck_time = 0.;
+        }
+
+        if goal_moved || stuck {
+            self.goal = Some(goal);
+            self.waypoints = grid
+                .find_path(pos, goal, &self.avoid)
+                .unwrap_or_default()
+                .into();
+        }
+
+        while self.waypoints.len() > 1 && pos.distance(self.waypoints[0]) < grid.cell_size() / 2. {
+            self.waypoints.pop_front();
+        }
+        self.waypoints.front().copied().unwrap_or(goal)
+    }
+}
+
+/// Only the walls and the blocking obstacles are in the grid, the bases are the goals
+pub fn build_nav_grid(
+    mut grid: ResMut<NavGrid>,
+    colliders_q: Query<(&Transform, &Collider)>,
+    changed_q: Query<&Collider, Changed<Collider>>,
+    mut removed_walls: RemovedComponents<ChickenCorralWall>,
+    mut removed_obstacles: RemovedComponents<Obstacle>,
+    layout: Res<MapLayout>,
+    config: Res<GameConfig>,
+) {
+    let removed = removed_walls.read().count() + removed_obstacles.read().count() > 0;
+    let changed = changed_q.iter().any(|collider| collider.layer.is_static());
+    if !removed && !changed && !layout.is_changed() && !config.is_changed() {
+        return;
+    }
+
+    let colliders = colliders_q
+        .iter()
+        .filter(|(_, collider)| {
+            matches!(
+                collider.layer,
+                CollisionLayer::Fence | CollisionLayer::Gate | CollisionLayer::Obstacle
+            )
+        })
+        .map(|(pos, collider)| (pos.translation.xy(), collider.shape));
+    *grid = NavGrid::new(
+        &layout,
+        config.werewolf.nav_cell_size,
+        config.werewolf.size / 2.,
+        colliders,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Biome;
+
+    // the arena from -100 to 100, the cells are 20 wide, so their centers are at -90, -70, ...
+    const ARENA_SIZE: f32 = 200.;
+    const CELL_SIZE: f32 = 20.;
+    // the thin walls between the cell centers are still blocking the cells
+    const CLEARANCE: f32 = CELL_SIZE / 2.;
+
+    fn grid(colliders: &[(Vec2, Shape)]) -> NavGrid {
+        let layout = MapLayout::with_ground(ARENA_SIZE, CELL_SIZE, |_| Biome::Grass);
+        NavGrid::new(&layout, CELL_SIZE, CLEARANCE, colliders.iter().copied())
+    }
+
+    fn wall(center: Vec2, size: Vec2) -> (Vec2, Shape) {
+        (
+            center,
+            Shape::Rect {
+                half_size: size / 2.,
+            },
+        )
+    }
+
+    // the mover can go straight from every waypoint to the next one
+    fn assert_walkable(grid: &NavGrid, from: Vec2, path: &[Vec2], avoid: &[IVec2]) {
+        let mut current = from;
+        for point in path {
+            assert!(
+                grid.line_of_sight(current, *point, avoid),
+                "{} can't be seen from {} in {:?}",
+                point,
+                current,
+                path
+            );
+            current = *point;
+        }
+    }
+
+    #[test]
+    fn path_goes_around_the_wall() {
+        // the wall from the bottom of the arena, the way is free only above it
+        let grid = grid(&[wall(Vec2::new(0., -20.), Vec2::new(10., 160.))]);
+        let (from, to) = (Vec2::new(-50., -50.), Vec2::new(50., -50.));
+
+        let path = grid.find_path(from, to, &[]).unwrap();
+
+        assert_eq!(path.last(), Some(&to));
+        assert!(path.len() > 1);
+        assert!(path.iter().any(|point| point.y > 60.), "{:?}", path);
+        assert_walkable(&grid, from, &path, &[]);
+    }
+
+    #[test]
+    fn unreachable_goal_has_no_path() {
+        // the wall over the whole arena
+        let grid = grid(&[wall(Vec2::ZERO, Vec2::new(10., ARENA_SIZE))]);
+
+        let path = grid.find_path(Vec2::new(-50., 0.), Vec2::new(50., 0.), &[]);
+
+        assert_eq!(path, None);
+    }
+
+    #[test]
+    fn blocked_goal_is_reached_from_the_nearest_free_cell() {
+        let rock = (Vec2::new(50., 10.), Shape::Circle { radius: 15. });
+        let grid = grid(&[rock]);
+        let (from, to) = (Vec2::new(-50., 10.), Vec2::new(50., 10.));
+        let goal_cell = grid.cell_of(to).unwrap();
+        assert!(!grid.is_free(goal_cell, &[]));
+
+        let path = grid.find_path(from, to, &[]).unwrap();
+
+        // the last step is going straight into the rock, the collisions are stopping the mover
+        let (last, before_last) = (path[path.len() - 1], path[path.len() - 2]);
+        assert_eq!(last, to);
+        let snapped = grid.cell_of(before_last).unwrap();
+        assert_eq!(
+            snapped,
+            grid.nearest_free(goal_cell, &[]).unwrap(),
+            "{:?}",
+            path
+        );
+        assert_eq!(before_last, grid.center_of(snapped));
+        assert_walkable(&grid, from, &path[..path.len() - 1], &[]);
+    }
+
+    #[test]
+    fn same_input_gives_same_path() {
+        let colliders = [
+            wall(Vec2::new(0., -20.), Vec2::new(10., 160.)),
+            (Vec2::new(-40., 50.), Shape::Circle { radius: 12. }),
+        ];
+        let (from, to) = (Vec2::new(-80., -80.), Vec2::new(80., -70.));
+        let avoid = [IVec2::new(6, 8)];
+
+        let first = grid(&colliders).find_path(from, to, &avoid);
+        for _ in 0..10 {
+            assert_eq!(grid(&colliders).find_path(from, to, &avoid), first);
+        }
+        assert!(first.is_some());
+    }
+
+    #[test]
+    fn avoided_cells_force_a_detour() {
+        let grid = grid(&[]);
+        let (from, to) = (Vec2::new(-50., 10.), Vec2::new(50., 10.));
+        assert_eq!(grid.find_path(from, to, &[]), Some(vec![to]));
+
+        // the cells on the straight way are taken as blocked
+        let avoid = [
+            grid.cell_of(Vec2::new(0., 10.)).unwrap(),
+            grid.cell_of(Vec2::new(0., -10.)).unwrap(),
+        ];
+        let path = grid.find_path(from, to, &avoid).unwrap();
+
+        assert_eq!(path.last(), Some(&to));
+        assert!(path.len() > 1, "{:?}", path);
+        assert_walkable(&grid, from, &path, &avoid);
+    }
+
+    #[test]
+    fn stuck_mover_plans_a_new_path() {
+        let grid = grid(&[]);
+        let config = GameConfig::default();
+        let (pos, goal) = (Vec2::new(-50., 10.), Vec2::new(50., 10.));
+        let mut path = NavPath::default();
+        let delta = config.werewolf.replan_delay / 4.;
+
+        // the way is free, so the mover is going straight to the goal
+        assert_eq!(path.next_point(pos, goal, 1., delta, &grid, &config), goal);
+
+        // something, which is not in the grid, is holding the mover on the same place
+        let mut next = goal;
+        for _ in 0..4 {
+            next = path.next_point(pos, goal, 1., delta, &grid, &config);
+        }
+
+        assert_ne!(next, goal);
+        assert_eq!(path.avoid, vec![grid.cell_of(goal).unwrap()]);
+    }
+}
@@ -1,35 +1,106 @@
 use bevy::{
+    ecs::entity::{EntityMapper, MapEntities},
     prelude::*,
-    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+    sprite::MaterialMesh2dBundle,
 };
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     base::{Base, BaseCatchingRadius},
-    chicken::Chicken,
-    settings::*,
+    chicken::{Chicken, ChickenBundle},
+    config::{config_reloaded, GameConfig},
+    map::MapLayout,
+    misc::get_random_dir,
+    rng::GameRng,
+    spawning::{radius_mesh, spawn_player},
+    state::{GameState, InRound, SpawnSet},
     ui::EvSpawnPopup,
+    werewolf::{werewolf_behave, ForWerewolf},
     PlayerRes,
 };
 
+pub struct PlayerPlugin;
+
+impl Plugin for PlayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EvPlayerLvlup>();
+        app.add_event::<EvPlayerHit>();
+        app.add_event::<EvBaseLooted>();
+        // the popup is shown by the ui plugin, but the player is sending it
+        app.add_event::<EvSpawnPopup>();
+        app.init_resource::<GameConfig>();
+        app.init_resource::<PlayerRes>();
+        app.init_resource::<GameRng>();
+        app.init_resource::<MapLayout>();
+
+        app.add_systems(OnEnter(InRound), spawn_player.in_set(SpawnSet::Entities));
+        app.add_systems(
+            Update,
+            (
+                move_player,
+                player_lvlup,
+                // the werewolves are hitting the player in their behaviour
+                player_hit.after(werewolf_behave),
+                respawn_player,
+                recover_from_slow,
+            )
+                .run_if(in_state(GameState::Playing)),
+        );
+        app.add_systems(Update, apply_config_to_player.run_if(config_reloaded));
+        app.add_systems(
+            FixedUpdate,
+            (
+                player_chicken_collision,
+                catch_chicken,
+                try_give_chickens_to_base,
+                loot_werewolf_base,
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+
+        app.observe(on_add_catchable);
+        app.observe(on_remove_catchable);
+        app.observe(on_knocked_out);
+        app.observe(on_woke_up);
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
 pub enum LvlupType {
     Speed,
     CatchingRadius,
+    Sight,
 }
 
 #[derive(Event)]
 pub struct EvPlayerLvlup(pub LvlupType);
 
+/// The player was hit by a werewolf
+#[derive(Event)]
+pub struct EvPlayerHit {
+    pub damage: f32,
+}
+
+/// The player started to loot the base of a werewolf, the werewolf is going to defend it
+#[derive(Event)]
+pub struct EvBaseLooted {
+    pub base: Entity,
+}
+
 #[derive(Component)]
 pub struct ForPlayer;
 
 #[derive(Component)]
 pub struct PlayerCatchingRadius;
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Player {
     pub corral: Option<Entity>,
     pub speed_multiplier: f32,
     pub catching_radius_multiplier: f32,
+    pub sight_multiplier: f32,
     k_up: KeyCode,
     k_down: KeyCode,
     k_left: KeyCode,
@@ -37,53 +108,115 @@ pub struct Player {
     k_catch: KeyCode,
     k_give: KeyCode,
     k_upgrade: KeyCode,
+    k_loot: KeyCode,
 }
 
-impl Default for Player {
-    fn default() -> Self {
+impl Player {
+    pub fn from_config(config: &GameConfig) -> Self {
         Self {
             corral: None,
-            speed_multiplier: PLAYER_DEFAULT_SPEED_MULTIPLIER,
-            catching_radius_multiplier: PLAYER_DEFAULT_CATCHING_RADIUS_MULTIPLIER,
-            k_up: PLAYER_KEY_UP,
-            k_down: PLAYER_KEY_DOWN,
-            k_left: PLAYER_KEY_LEFT,
-            k_right: PLAYER_KEY_RIGHT,
-            k_catch: PLAYER_KEY_CATCH,
-            k_give: PLAYER_KEY_GIVE,
-            k_upgrade: PLAYER_KEY_UPGRADE,
+            speed_multiplier: config.player.default_speed_multiplier,
+            catching_radius_multiplier: config.player.default_catching_radius_multiplier,
+            sight_multiplier: config.player.default_sight_multiplier,
+            k_up: config.player.key_up,
+            k_down: config.player.key_down,
+            k_left: config.player.key_left,
+            k_right: config.player.key_right,
+            k_catch: config.player.key_catch,
+            k_give: config.player.key_give,
+            k_upgrade: config.player.key_upgrade,
+            k_loot: config.player.key_loot,
         }
     }
+
+    pub fn set_keys(&mut self, config: &GameConfig) {
+        self.k_up = config.player.key_up;
+        self.k_down = config.player.key_down;
+        self.k_left = config.player.key_left;
+        self.k_right = config.player.key_right;
+        self.k_catch = config.player.key_catch;
+        self.k_give = config.player.key_give;
+        self.k_upgrade = config.player.key_upgrade;
+        self.k_loot = config.player.key_loot;
+    }
+
+    pub fn speed_up(&mut self, config: &GameConfig) {
+        self.speed_multiplier += config.player.lvlup_speed;
+    }
+    pub fn catch_radius_up(&mut self, config: &GameConfig) {
+        self.catching_radius_multiplier += config.player.lvlup_catching_radius;
+    }
+    pub fn sight_up(&mut self, config: &GameConfig) {
+        self.sight_multiplier += config.player.lvlup_sight;
+    }
+
+    /// Everything further is in the fog, see fog.rs
+    pub fn sight_radius(&self, config: &GameConfig) -> f32 {
+        config.player.sight_distance * self.sight_multiplier
+    }
 }
 
-impl Player {
-    pub fn speed_up(&mut self) {
-        self.speed_multiplier += PLAYER_LVLUP_SPEED;
+impl MapEntities for Player {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.corral = self.corral.map(|corral| entity_mapper.map_entity(corral));
     }
-    pub fn catch_radius_up(&mut self) {
-        self.catching_radius_multiplier += PLAYER_LVLUP_CATCHING_RADIUS;
+}
+
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Health {
+    pub fn from_config(config: &GameConfig) -> Self {
+        Self {
+            current: config.player.max_health,
+            max: config.player.max_health,
+        }
     }
 }
 
+/// The player has no health anymore, it can't do anything, until it is respawned in the base
+#[derive(Component)]
+pub struct KnockedOut(pub Timer);
+
+/// The player was pecked by an aggressive chicken and is slower for a while
+#[derive(Component)]
+pub struct Slowed(pub Timer);
+
+/// The player is holding the loot key in the base of a werewolf
+#[derive(Component)]
+pub struct Looting {
+    pub base: Entity,
+    pub timer: Timer,
+}
+
 #[derive(Bundle)]
 pub struct PlayerBundle {
     sprite_bundle: SpriteBundle,
     player: Player,
+    health: Health,
 }
 
-impl Default for PlayerBundle {
-    fn default() -> Self {
+impl PlayerBundle {
+    pub fn from_config(config: &GameConfig) -> Self {
+        Self::new(Player::from_config(config), Vec2::ZERO, config)
+    }
+
+    pub fn new(player: Player, pos: Vec2, config: &GameConfig) -> Self {
         Self {
             sprite_bundle: SpriteBundle {
-                transform: Transform::from_xyz(0., 0., PLAYER_Z),
+                transform: Transform::from_translation(pos.extend(config.player.z)),
                 sprite: Sprite {
                     color: Color::WHITE,
-                    custom_size: Some(Vec2::new(PLAYER_SIZE, PLAYER_SIZE)),
+                    custom_size: Some(Vec2::new(config.player.size, config.player.size)),
                     ..Default::default()
                 },
                 ..Default::default()
             },
-            player: Player::default(),
+            player,
+            health: Health::from_config(config),
         }
     }
 }
@@ -99,6 +232,7 @@ pub fn player_chicken_collision(
         (&Transform, Entity),
         (With<Chicken>, Without<Base>, Without<BaseCatchingRadius>),
     >,
+    config: Res<GameConfig>,
 ) {
     let (p_pos, player) = player_q.get_single().unwrap();
     // if there are one chicken to catch
@@ -118,7 +252,7 @@ pub fn player_chicken_collision(
                     player_res.catchable_chicken = Some(ch_ent);
                 // and this chicken ran away too far
                 } else if p_pos.translation.distance(catchable_ch_pos.translation)
-                    >= PLAYER_CATCHING_RADIUS * player.catching_radius_multiplier
+                    >= config.player.catching_radius * player.catching_radius_multiplier
                 {
                     // make this chicken not catchable
                     commands
@@ -135,7 +269,7 @@ pub fn player_chicken_collision(
     } else {
         for (ch_pos, ch_ent) in chickens_q.iter() {
             if p_pos.translation.distance(ch_pos.translation)
-                < PLAYER_CATCHING_RADIUS * player.catching_radius_multiplier
+                < config.player.catching_radius * player.catching_radius_multiplier
             {
                 // and make it cathable
                 player_res.catchable_chicken = Some(ch_ent);
@@ -149,12 +283,14 @@ pub fn player_chicken_collision(
 // todo! the function that sends events, after the player presses the control key
 pub fn try_give_chickens_to_base(
     mut base_q: Query<(&Transform, &mut Base), (Without<Player>, With<ForPlayer>)>,
-    player_q: Query<(&Transform, &Player), Without<Base>>,
+    player_q: Query<(&Transform, &Player), (Without<Base>, Without<KnockedOut>)>,
     input: Res<ButtonInput<KeyCode>>,
     mut player_res: ResMut<PlayerRes>,
 ) {
     let (b_pos, mut base) = base_q.get_single_mut().unwrap();
-    let (p_pos, player) = player_q.get_single().unwrap();
+    let Ok((p_pos, player)) = player_q.get_single() else {
+        return;
+    };
 
     if p_pos.translation.distance(b_pos.translation) <= base.radius && input.pressed(player.k_give)
     {
@@ -163,28 +299,114 @@ pub fn try_give_chickens_to_base(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn catch_chicken(
     mut commands: Commands,
-    player_q: Query<&Player>,
+    player_q: Query<(&Player, Has<KnockedOut>)>,
+    mut chickens_q: Query<(&mut Chicken, &mut Sprite)>,
     mut player_res: ResMut<PlayerRes>,
     input: Res<ButtonInput<KeyCode>>,
     mut popup_ev: EventWriter<EvSpawnPopup>,
+    mut rng: ResMut<GameRng>,
+    config: Res<GameConfig>,
 ) {
-    let player = player_q.get_single().unwrap();
+    let (player, knocked_out) = player_q.get_single().unwrap();
 
-    if input.just_pressed(player.k_catch) && player_res.catchable_chicken.is_some() {
-        if player_res.inventory_chickens_amount >= PLAYER_MAX_INVENTORY_SPACE {
-            popup_ev.send_default();
-        } else {
-            commands
-                .entity(player_res.catchable_chicken.unwrap())
-                .despawn_recursive();
-            player_res.catchable_chicken = None;
-            player_res.inventory_chickens_amount += 1;
+    if !knocked_out && input.just_pressed(player.k_catch) && player_res.catchable_chicken.is_some()
+    {
+        let ch_ent = player_res.catchable_chicken.unwrap();
+        if player_res.inventory_chickens_amount >= config.player.max_inventory_space {
+            popup_ev.send(EvSpawnPopup("The max chickens are catched".to_string()));
+            return;
+        }
+
+        // the aggressive chicken must be catched more times, the others can become aggressive
+        if let Ok((mut chicken, mut ch_sprite)) = chickens_q.get_mut(ch_ent) {
+            if chicken.is_aggressive() {
+                if !chicken.struggle() {
+                    return;
+                }
+            } else if rng.gen_bool(config.chicken.aggressive_chance) {
+                chicken.make_aggressive(&config);
+                ch_sprite.color = chicken.sprite_color(true, &config);
+                return;
+            }
+        }
+
+        commands.entity(ch_ent).despawn_recursive();
+        player_res.catchable_chicken = None;
+        player_res.inventory_chickens_amount += 1;
+    }
+}
+
+/// Takes the chickens from the werewolf base one by one, while the loot key is held in there
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn loot_werewolf_base(
+    mut commands: Commands,
+    mut player_q: Query<(
+        Entity,
+        &Transform,
+        &Player,
+        Has<KnockedOut>,
+        Option<&mut Looting>,
+    )>,
+    mut bases_q: Query<(Entity, &Transform, &mut Base), (With<ForWerewolf>, Without<Player>)>,
+    mut player_res: ResMut<PlayerRes>,
+    input: Res<ButtonInput<KeyCode>>,
+    mut looted_ev: EventWriter<EvBaseLooted>,
+    mut popup_ev: EventWriter<EvSpawnPopup>,
+    time: Res<Time>,
+    config: Res<GameConfig>,
+) {
+    let Ok((player_ent, p_pos, player, knocked_out, looting)) = player_q.get_single_mut() else {
+        return;
+    };
+
+    let base = bases_q.iter_mut().find(|(_, b_pos, base)| {
+        p_pos.translation.xy().distance(b_pos.translation.xy()) <= base.radius
+    });
+    let Some((base_ent, _, mut base)) =
+        base.filter(|_| !knocked_out && input.pressed(player.k_loot))
+    else {
+        // releasing the key or leaving the base is starting the looting from the beginning
+        if looting.is_some() {
+            commands.entity(player_ent).remove::<Looting>();
+        }
+        return;
+    };
+
+    let Some(mut looting) = looting.filter(|looting| looting.base == base_ent) else {
+        commands.entity(player_ent).insert(Looting {
+            base: base_ent,
+            timer: Timer::from_seconds(config.player.loot_delta, TimerMode::Repeating),
+        });
+        looted_ev.send(EvBaseLooted { base: base_ent });
+        return;
+    };
+
+    if looting.timer.tick(time.delta()).just_finished() && base.chickens_amount > 0 {
+        if player_res.inventory_chickens_amount >= config.player.max_inventory_space {
+            popup_ev.send(EvSpawnPopup("The max chickens are catched".to_string()));
+            return;
+        }
+        base.chickens_amount -= 1;
+        player_res.inventory_chickens_amount += 1;
+    }
+}
+
+pub fn recover_from_slow(
+    mut commands: Commands,
+    mut player_q: Query<(Entity, &mut Slowed)>,
+    time: Res<Time>,
+) {
+    for (player_ent, mut slowed) in player_q.iter_mut() {
+        if slowed.0.tick(time.delta()).finished() {
+            commands.entity(player_ent).remove::<Slowed>();
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn player_lvlup(
     mut lvlup_ev: EventReader<EvPlayerLvlup>,
     mut commands: Commands,
@@ -193,6 +415,7 @@ pub fn player_lvlup(
     p_catch_rad_q: Query<Entity, With<PlayerCatchingRadius>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut material: ResMut<Assets<ColorMaterial>>,
+    config: Res<GameConfig>,
 ) {
     let mut player = player_q.get_single_mut().unwrap();
     let mut p_base = player_base_q.get_single_mut().unwrap();
@@ -200,54 +423,65 @@ pub fn player_lvlup(
     for lvlup_type in lvlup_ev.read() {
         match lvlup_type.0 {
             LvlupType::Speed => {
-                if p_base.chickens_amount >= PLAYER_LVLUP_SPEED_PRICE {
-                    player.speed_up();
-                    p_base.chickens_amount -= PLAYER_LVLUP_SPEED_PRICE;
+                if p_base.chickens_amount >= config.player.lvlup_speed_price {
+                    player.speed_up(&config);
+                    p_base.chickens_amount -= config.player.lvlup_speed_price;
                 }
             }
             LvlupType::CatchingRadius => {
-                if p_base.chickens_amount >= PLAYER_LVLUP_CATCHING_RADIUS_PRICE {
-                    player.catch_radius_up();
-                    p_base.chickens_amount -= PLAYER_LVLUP_CATCHING_RADIUS_PRICE;
+                if p_base.chickens_amount >= config.player.lvlup_catching_radius_price {
+                    player.catch_radius_up(&config);
+                    p_base.chickens_amount -= config.player.lvlup_catching_radius_price;
 
+                    let catching_radius =
+                        config.player.catching_radius * player.catching_radius_multiplier;
                     commands.entity(p_catch_rad_q.get_single().unwrap()).insert(
                         MaterialMesh2dBundle {
-                            mesh: Mesh2dHandle(meshes.add(Annulus::new(
-                                PLAYER_CATCHING_RADIUS * player.catching_radius_multiplier - 1.,
-                                PLAYER_CATCHING_RADIUS * player.catching_radius_multiplier,
-                            ))),
-                            material: material.add(BASE_PLAYER_CATCHING_RADIUS_COLOR),
+                            mesh: radius_mesh(&mut meshes, catching_radius),
+                            material: material.add(config.player.catching_radius_color),
                             ..Default::default()
                         },
                     );
                 }
             }
+            LvlupType::Sight => {
+                if p_base.chickens_amount >= config.player.lvlup_sight_price {
+                    player.sight_up(&config);
+                    p_base.chickens_amount -= config.player.lvlup_sight_price;
+                }
+            }
         };
     }
 }
 
 pub fn on_add_catchable(
     trigger: Trigger<OnAdd, ForPlayerCatchable>,
-    mut chickens_q: Query<&mut Sprite, With<Chicken>>,
+    mut chickens_q: Query<(&Chicken, &mut Sprite)>,
+    config: Res<GameConfig>,
 ) {
-    let mut ch_sprite = chickens_q.get_mut(trigger.entity()).unwrap();
-    ch_sprite.color = CHICKEN_COLOR.mix(&Color::BLACK, 0.5);
+    let (chicken, mut ch_sprite) = chickens_q.get_mut(trigger.entity()).unwrap();
+    ch_sprite.color = chicken.sprite_color(true, &config);
 }
 
 pub fn on_remove_catchable(
     trigger: Trigger<OnRemove, ForPlayerCatchable>,
-    mut chickens_q: Query<&mut Sprite, With<Chicken>>,
+    mut chickens_q: Query<(&Chicken, &mut Sprite)>,
+    config: Res<GameConfig>,
 ) {
-    let mut ch_sprite = chickens_q.get_mut(trigger.entity()).unwrap();
-    ch_sprite.color = CHICKEN_COLOR;
+    let (chicken, mut ch_sprite) = chickens_q.get_mut(trigger.entity()).unwrap();
+    ch_sprite.color = chicken.sprite_color(false, &config);
 }
 
 pub fn move_player(
-    mut player_q: Query<(&mut Transform, &Player)>,
+    mut player_q: Query<(&mut Transform, &Player, Has<Slowed>), Without<KnockedOut>>,
     input: Res<ButtonInput<KeyCode>>,
     time: Res<Time>,
+    layout: Res<MapLayout>,
+    config: Res<GameConfig>,
 ) {
-    let (mut p_pos, player) = player_q.single_mut();
+    let Ok((mut p_pos, player, slowed)) = player_q.get_single_mut() else {
+        return;
+    };
     let mut move_dir = Vec2::new(0., 0.);
 
     if input.pressed(player.k_up) {
@@ -263,6 +497,125 @@ pub fn move_player(
         move_dir += Vec2::new(1., 0.);
     }
 
-    p_pos.translation +=
-        move_dir.extend(0.) * PLAYER_SPEED * player.speed_multiplier * time.delta_seconds();
+    let ground_speed = layout.speed_multiplier_at(p_pos.translation.xy(), &config);
+    let slowdown = if slowed {
+        config.chicken.peck_slowdown
+    } else {
+        1.
+    };
+    p_pos.translation += move_dir.extend(0.)
+        * config.player.speed
+        * player.speed_multiplier
+        * ground_speed
+        * slowdown
+        * time.delta_seconds();
+}
+
+// the upgrades of the player are staying, only the base values are changed
+pub fn apply_config_to_player(
+    mut commands: Commands,
+    mut player_q: Query<(&mut Player, &mut Health, &mut Sprite)>,
+    p_catch_rad_q: Query<Entity, With<PlayerCatchingRadius>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut material: ResMut<Assets<ColorMaterial>>,
+    config: Res<GameConfig>,
+) {
+    let Ok((mut player, mut health, mut sprite)) = player_q.get_single_mut() else {
+        return;
+    };
+    player.set_keys(&config);
+    health.max = config.player.max_health;
+    health.current = health.current.min(health.max);
+    sprite.custom_size = Some(Vec2::new(config.player.size, config.player.size));
+
+    let catching_radius = config.player.catching_radius * player.catching_radius_multiplier;
+    for radius_ent in p_catch_rad_q.iter() {
+        commands.entity(radius_ent).insert((
+            radius_mesh(&mut meshes, catching_radius),
+            material.add(config.player.catching_radius_color),
+        ));
+    }
+}
+
+// every hit is taking the health and the catched chickens are running away
+pub fn player_hit(
+    mut commands: Commands,
+    mut hit_ev: EventReader<EvPlayerHit>,
+    mut player_q: Query<(Entity, &Transform, &mut Health, Has<KnockedOut>), With<Player>>,
+    mut player_res: ResMut<PlayerRes>,
+    mut rng: ResMut<GameRng>,
+    config: Res<GameConfig>,
+) {
+    let Ok((player_ent, p_pos, mut health, mut knocked_out)) = player_q.get_single_mut() else {
+        return;
+    };
+
+    for hit in hit_ev.read() {
+        if knocked_out {
+            continue;
+        }
+        health.current = (health.current - hit.damage).max(0.);
+
+        for _ in 0..player_res.inventory_chickens_amount {
+            let drop_pos = p_pos.translation.xy() + get_random_dir(&mut rng) * config.player.size;
+            commands.spawn((
+                ChickenBundle::new(
+                    Chicken::from_config(&config),
+                    drop_pos.extend(config.chicken.z),
+                    &config,
+                ),
+                StateScoped(InRound),
+            ));
+        }
+        player_res.inventory_chickens_amount = 0;
+
+        if health.current <= 0. {
+            knocked_out = true;
+            commands
+                .entity(player_ent)
+                .insert(KnockedOut(Timer::from_seconds(
+                    config.player.knockout_delta,
+                    TimerMode::Once,
+                )));
+        }
+    }
+}
+
+pub fn respawn_player(
+    mut commands: Commands,
+    mut player_q: Query<(Entity, &mut Transform, &mut Health, &mut KnockedOut)>,
+    player_base_q: Query<&Transform, (With<Base>, With<ForPlayer>, Without<KnockedOut>)>,
+    time: Res<Time>,
+    config: Res<GameConfig>,
+) {
+    let Ok((player_ent, mut p_pos, mut health, mut knocked_out)) = player_q.get_single_mut() else {
+        return;
+    };
+    if !knocked_out.0.tick(time.delta()).finished() {
+        return;
+    }
+
+    if let Ok(b_pos) = player_base_q.get_single() {
+        p_pos.translation = b_pos.translation.xy().extend(config.player.z);
+    }
+    health.current = health.max;
+    commands.entity(player_ent).remove::<KnockedOut>();
+}
+
+pub fn on_knocked_out(
+    trigger: Trigger<OnAdd, KnockedOut>,
+    mut player_q: Query<&mut Sprite, With<Player>>,
+) {
+    let mut p_sprite = player_q.get_mut(trigger.entity()).unwrap();
+    p_sprite.color = Color::WHITE.mix(&Color::BLACK, 0.5);
+}
+
+pub fn on_woke_up(
+    trigger: Trigger<OnRemove, KnockedOut>,
+    mut player_q: Query<&mut Sprite, With<Player>>,
+) {
+    // the player can be despawned knocked out at the end of the round
+    if let Ok(mut p_sprite) = player_q.get_mut(trigger.entity()) {
+        p_sprite.color = Color::WHITE;
+    }
 }
@@ -0,0 +1,343 @@
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::{input::InputSystem, prelude::*, time::TimeUpdateStrategy};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::GameConfig,
+    map::PendingMapFile,
+    player::{EvPlayerLvlup, LvlupType},
+    rng::{reset_rng, GameRng},
+    state::{GameState, InRound},
+};
+
+/// Is written in every replay file, the files with the other version are not played
+pub const REPLAY_VERSION: u32 = 1;
+
+/// Records the rounds or plays them back.
+///
+/// The recording is started, when the app has the ReplayRecorder resource, and the last round is
+/// written to the file after it is over (or the app is closed). The replay is started, when the
+/// app has the ReplayPlayer resource, it starts the round with the recorded seed and config and
+/// gives it the recorded input and time steps frame by frame. The config of the replay must be
+/// inserted by the app itself, before the plugins are added (see main.rs).
+///
+/// Only the keyboard and the lvlup events are recorded, the clicks in the menus are not.
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>();
+        app.init_resource::<PendingMapFile>();
+        app.add_event::<EvPlayerLvlup>();
+
+        app.add_systems(
+            OnEnter(InRound),
+            start_recording
+                .after(reset_rng)
+                .run_if(resource_exists::<ReplayRecorder>),
+        );
+        app.add_systems(
+            PostUpdate,
+            record_frame.run_if(resource_exists::<ReplayRecorder>.and_then(in_state(InRound))),
+        );
+        app.add_systems(
+            OnExit(InRound),
+            write_recording.run_if(resource_exists::<ReplayRecorder>),
+        );
+        app.add_systems(
+            Last,
+            write_recording_on_exit.run_if(resource_exists::<ReplayRecorder>),
+        );
+
+        app.add_systems(Update, start_replay.run_if(resource_exists::<ReplayPlayer>));
+        // the recorded input is replacing the real one before any game system is running
+        app.add_systems(
+            PreUpdate,
+            feed_replay
+                .after(InputSystem)
+                .run_if(resource_exists::<ReplayPlayer>),
+        );
+    }
+
+    // the replay is starting the round by itself, so the time of the first frame can be set
+    // (the headless plugin is starting the round right away)
+    fn finish(&self, app: &mut App) {
+        if app.world().contains_resource::<ReplayPlayer>() {
+            app.insert_state(GameState::MainMenu);
+        }
+    }
+}
+
+/// The input of the player in one frame
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct ReplayFrame {
+    pub delta: Duration,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pressed: Vec<KeyCode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub just_pressed: Vec<KeyCode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub just_released: Vec<KeyCode>,
+    /// the upgrades from the lvlup screen
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lvlups: Vec<LvlupType>,
+}
+
+/// One round with everything, what is needed to play it again
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u64,
+    pub config: GameConfig,
+    pub frames: Vec<ReplayFrame>,
+}
+
+// only the version is read first, so the old files are not giving the parse errors
+#[derive(Deserialize)]
+struct ReplayVersion {
+    version: u32,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    Serialize(ron::Error),
+    Parse(ron::error::SpannedError),
+    Version(u32),
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "can't access the replay file: {}", err),
+            ReplayError::Serialize(err) => write!(f, "can't write the replay: {}", err),
+            ReplayError::Parse(err) => write!(f, "can't parse the replay file: {}", err),
+            ReplayError::Version(version) => write!(
+                f,
+                "the replay file has version {}, but only version {} is supported",
+                version, REPLAY_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<std::io::Error> for ReplayError {
+    fn from(err: std::io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+impl From<ron::Error> for ReplayError {
+    fn from(err: ron::Error) -> Self {
+        ReplayError::Serialize(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for ReplayError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        ReplayError::Parse(err)
+    }
+}
+
+impl Replay {
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), ReplayError> {
+        let replay = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(path, replay)?;
+
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        let file = fs::read_to_string(path)?;
+
+        let ReplayVersion { version } = ron::from_str(&file)?;
+        if version != REPLAY_VERSION {
+            return Err(ReplayError::Version(version));
+        }
+
+        Ok(ron::from_str(&file)?)
+    }
+
+    /// The config, with which the round was recorded
+    pub fn seeded_config(&self) -> GameConfig {
+        GameConfig {
+            seed: Some(self.seed),
+            ..self.config.clone()
+        }
+    }
+}
+
+/// Is inserted by the app, when the rounds must be recorded
+#[derive(Resource)]
+pub struct ReplayRecorder {
+    path: PathBuf,
+    replay: Option<Replay>,
+}
+
+impl ReplayRecorder {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            replay: None,
+        }
+    }
+}
+
+/// Is inserted by the app, when the replay must be played
+#[derive(Resource)]
+pub struct ReplayPlayer {
+    replay: Replay,
+    next_frame: usize,
+    started: bool,
+    // the strategy of the app is set back after the replay
+    time_strategy: Option<TimeUpdateStrategy>,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            next_frame: 0,
+            started: false,
+            time_strategy: None,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.started && self.next_frame >= self.replay.frames.len()
+    }
+}
+
+pub fn start_recording(
+    mut recorder: ResMut<ReplayRecorder>,
+    rng: Res<GameRng>,
+    pending: Res<PendingMapFile>,
+    config: Res<GameConfig>,
+) {
+    // the map from the editor is played again with the replay
+    let mut config = config.clone();
+    config.map.file = pending.path(&config).cloned();
+
+    // only the last round is kept
+    recorder.replay = Some(Replay {
+        version: REPLAY_VERSION,
+        seed: rng.seed(),
+        config,
+        frames: Vec::new(),
+    });
+}
+
+pub fn record_frame(
+    mut recorder: ResMut<ReplayRecorder>,
+    input: Res<ButtonInput<KeyCode>>,
+    mut lvlup_ev: EventReader<EvPlayerLvlup>,
+    time: Res<Time<Virtual>>,
+) {
+    let Some(replay) = recorder.replay.as_mut() else {
+        return;
+    };
+
+    replay.frames.push(ReplayFrame {
+        delta: time.delta(),
+        pressed: input.get_pressed().copied().collect(),
+        just_pressed: input.get_just_pressed().copied().collect(),
+        just_released: input.get_just_released().copied().collect(),
+        lvlups: lvlup_ev.read().map(|lvlup| lvlup.0).collect(),
+    });
+}
+
+pub fn write_recording(mut recorder: ResMut<ReplayRecorder>) {
+    let Some(replay) = recorder.replay.take() else {
+        return;
+    };
+
+    match replay.write(&recorder.path) {
+        Ok(()) => info!(
+            "replay with {} frames written to {}",
+            replay.frames.len(),
+            recorder.path.display()
+        ),
+        Err(err) => error!("{}: {}", recorder.path.display(), err),
+    }
+}
+
+// the round is not over, when the window is closed
+pub fn write_recording_on_exit(exit_ev: EventReader<AppExit>, recorder: ResMut<ReplayRecorder>) {
+    if !exit_ev.is_empty() {
+        write_recording(recorder);
+    }
+}
+
+pub fn start_replay(
+    mut player: ResMut<ReplayPlayer>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if player.started {
+        return;
+    }
+    player.started = true;
+
+    let Some(first_frame) = player.replay.frames.first() else {
+        warn!("the replay has no frames");
+        return;
+    };
+
+    // the time step of the next frame must be set before it is started
+    let first_delta = first_frame.delta;
+    player.time_strategy = Some(std::mem::replace(
+        &mut *time_strategy,
+        TimeUpdateStrategy::ManualDuration(first_delta),
+    ));
+    next_state.set(GameState::Playing);
+}
+
+pub fn feed_replay(
+    mut player: ResMut<ReplayPlayer>,
+    mut input: ResMut<ButtonInput<KeyCode>>,
+    mut lvlup_ev: EventWriter<EvPlayerLvlup>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+) {
+    if !player.started || player.is_finished() {
+        return;
+    }
+    let frame = &player.replay.frames[player.next_frame];
+
+    // the real input is ignored while replaying
+    input.reset_all();
+    for key in frame.pressed.iter() {
+        input.press(*key);
+        if !frame.just_pressed.contains(key) {
+            input.clear_just_pressed(*key);
+        }
+    }
+    for key in frame.just_released.iter() {
+        input.press(*key);
+        input.release(*key);
+        input.clear_just_pressed(*key);
+    }
+
+    for lvlup in frame.lvlups.iter() {
+        lvlup_ev.send(EvPlayerLvlup(*lvlup));
+    }
+
+    player.next_frame += 1;
+    let next_frame = player.next_frame;
+    match player.replay.frames.get(next_frame) {
+        Some(frame) => *time_strategy = TimeUpdateStrategy::ManualDuration(frame.delta),
+        None => {
+            if let Some(strategy) = player.time_strategy.take() {
+                *time_strategy = strategy;
+            }
+            info!("replay finished after {} frames", next_frame);
+        }
+    }
+}
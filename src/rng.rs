@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    config::GameConfig,
+    state::{InRound, SpawnSet},
+};
+
+/// Every random thing in the game is taken from the GameRng, so the same seed gives the same
+/// round (with the same input and the same time steps, see headless.rs).
+///
+/// The systems, which are using the GameRng, must be ordered between each other, else the order
+/// of the random numbers is changing from run to run
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>();
+        app.init_resource::<GameRng>();
+
+        // every round is starting with the seed again
+        app.add_systems(OnEnter(InRound), reset_rng.before(SpawnSet::Entities));
+    }
+}
+
+#[derive(Resource, Deref, DerefMut)]
+pub struct GameRng {
+    seed: u64,
+    #[deref]
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Without the seed in the config a random one is taken
+    pub fn from_config(config: &GameConfig) -> Self {
+        Self::from_seed(config.seed.unwrap_or_else(|| rand::thread_rng().gen()))
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl FromWorld for GameRng {
+    fn from_world(world: &mut World) -> Self {
+        GameRng::from_config(&world.get_resource_or_insert_with(GameConfig::default))
+    }
+}
+
+pub fn reset_rng(mut rng: ResMut<GameRng>, config: Res<GameConfig>) {
+    // without the seed in the config every round is different, the seed is logged, so the round
+    // can be reproduced with it
+    *rng = GameRng::from_config(&config);
+    info!("round seed: {}", rng.seed);
+}
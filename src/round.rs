@@ -0,0 +1,127 @@
+use bevy::prelude::*;
+
+use crate::{
+    base::Base,
+    config::GameConfig,
+    player::ForPlayer,
+    state::{GameState, InRound},
+    werewolf::ForWerewolf,
+    Game,
+};
+
+pub struct RoundPlugin;
+
+impl Plugin for RoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>();
+        app.add_event::<EvRoundEnd>();
+
+        // the summary of the last round is shown until the next one is started
+        app.add_systems(OnEnter(InRound), |mut commands: Commands| {
+            commands.remove_resource::<RoundSummary>();
+        });
+        app.add_systems(
+            Update,
+            (tick_round_time, check_round_end)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundResult {
+    Won,
+    Lost,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundEndReason {
+    /// the player base has the target amount of chickens
+    TargetReached,
+    /// one of the werewolf bases has the target amount of chickens
+    WerewolfReachedTarget,
+    /// the time is over and the player base has more chickens than every werewolf base
+    OutCollected,
+    /// the time is over and some werewolf base has as many chickens as the player or more
+    OutCollectedByWerewolf,
+    /// the time is over and there was nothing else to do
+    Survived,
+}
+
+/// Is inserted as resource after the end of the round
+#[derive(Resource, Debug, Clone)]
+pub struct RoundSummary {
+    pub result: RoundResult,
+    pub reason: RoundEndReason,
+    pub player_chickens: usize,
+    pub werewolf_chickens: Vec<usize>,
+    /// how long the round was going (in seconds)
+    pub time: f32,
+}
+
+#[derive(Event)]
+pub struct EvRoundEnd(pub RoundSummary);
+
+pub fn tick_round_time(mut game: ResMut<Game>, time: Res<Time>) {
+    game.round_time.tick(time.delta());
+}
+
+pub fn check_round_end(
+    mut commands: Commands,
+    player_base_q: Query<&Base, With<ForPlayer>>,
+    werewolf_bases_q: Query<&Base, With<ForWerewolf>>,
+    game: Res<Game>,
+    config: Res<GameConfig>,
+    mut round_end_ev: EventWriter<EvRoundEnd>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Ok(player_base) = player_base_q.get_single() else {
+        return;
+    };
+    let player_chickens = player_base.chickens_amount;
+    let werewolf_chickens: Vec<usize> = werewolf_bases_q
+        .iter()
+        .map(|base| base.chickens_amount)
+        .collect();
+    let best_werewolf = werewolf_chickens.iter().copied().max().unwrap_or(0);
+    let round_time = game.round_time.elapsed_secs();
+
+    let mut end = None;
+
+    if let Some(target) = config.round.target_chickens {
+        if player_chickens >= target {
+            end = Some((RoundResult::Won, RoundEndReason::TargetReached));
+        } else if best_werewolf >= target {
+            end = Some((RoundResult::Lost, RoundEndReason::WerewolfReachedTarget));
+        }
+    }
+
+    if end.is_none() {
+        if let Some(time_limit) = config.round.time_limit {
+            if round_time >= time_limit {
+                end = Some(if !config.round.outcollect_werewolves {
+                    (RoundResult::Won, RoundEndReason::Survived)
+                } else if player_chickens > best_werewolf {
+                    (RoundResult::Won, RoundEndReason::OutCollected)
+                } else {
+                    (RoundResult::Lost, RoundEndReason::OutCollectedByWerewolf)
+                });
+            }
+        }
+    }
+
+    if let Some((result, reason)) = end {
+        let summary = RoundSummary {
+            result,
+            reason,
+            player_chickens,
+            werewolf_chickens,
+            time: round_time,
+        };
+
+        commands.insert_resource(summary.clone());
+        round_end_ev.send(EvRoundEnd(summary));
+        next_state.set(GameState::GameOver);
+    }
+}
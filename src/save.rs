@@ -0,0 +1,492 @@
+use std::{fmt::Display, fs, path::Path};
+
+use bevy::{
+    ecs::entity::{EntityHashMap, EntityMapper, MapEntities},
+    prelude::*,
+    time::Stopwatch,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    base::Base,
+    chicken::{Chicken, ChickenBundle},
+    chicken_corral::{ChickenCorral, ChickenCorralWall},
+    config::GameConfig,
+    fog::{spawn_fog_tiles, Explored, FogTile},
+    map::{spawn_ground_tiles, spawn_obstacle, GroundTile, MapLayout, Obstacle},
+    player::{ForPlayer, Health, Player},
+    spawning::{
+        spawn_base, spawn_corral, spawn_player_entity, spawn_walls_of_corral, spawn_werewolf,
+    },
+    state::{GameState, InRound},
+    werewolf::Werewolf,
+    Game, PlayerRes,
+};
+
+/// Is written in every save file, the files with the other version are not loaded
+pub const SAVE_VERSION: u32 = 12;
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>();
+        app.init_resource::<MapLayout>();
+        app.init_resource::<Explored>();
+        app.add_event::<EvSaveGame>();
+        app.add_event::<EvLoadGame>();
+
+        app.add_systems(Update, save_load_keys.run_if(in_state(GameState::Playing)));
+        app.add_systems(Update, save_game.run_if(in_state(InRound)));
+        // the game can be loaded from the main menu too, then the round is started first
+        app.add_systems(Update, load_game);
+        // the loaded world is there before the systems of the round are running
+        app.add_systems(
+            PreUpdate,
+            apply_loaded_game.run_if(resource_exists::<LoadedGame>.and_then(in_state(InRound))),
+        );
+    }
+}
+
+#[derive(Event, Default)]
+pub struct EvSaveGame;
+
+#[derive(Event, Default)]
+pub struct EvLoadGame;
+
+/// The save file was read, but the world is not replaced yet
+#[derive(Resource)]
+pub struct LoadedGame(pub SaveGame);
+
+/// The component of the entity with the things, which are not in the component itself
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SavedEntity<T> {
+    /// the entity at the time of saving, it is only used to remap the references on loading
+    pub entity: Entity,
+    pub translation: Vec3,
+    /// the player base and the player corral
+    #[serde(default)]
+    pub for_player: bool,
+    pub component: T,
+}
+
+impl<T> SavedEntity<T> {
+    pub fn new(entity: Entity, pos: &Transform, for_player: bool, component: T) -> Self {
+        Self {
+            entity,
+            translation: pos.translation,
+            for_player,
+            component,
+        }
+    }
+}
+
+/// Everything what is needed to continue the round
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SaveGame {
+    pub version: u32,
+    pub chicken_spawn_timer: Timer,
+    pub round_time: Stopwatch,
+    /// the ground is not saved as entities, it is spawned again from the layout
+    pub map: MapLayout,
+    pub explored: Explored,
+    pub inventory_chickens_amount: usize,
+    pub player: SavedEntity<Player>,
+    pub player_health: Health,
+    pub bases: Vec<SavedEntity<Base>>,
+    pub corrals: Vec<SavedEntity<ChickenCorral>>,
+    pub werewolves: Vec<SavedEntity<Werewolf>>,
+    pub chickens: Vec<SavedEntity<Chicken>>,
+    pub obstacles: Vec<SavedEntity<Obstacle>>,
+}
+
+// only the version is read first, so the old files are not giving the parse errors
+#[derive(Deserialize)]
+struct SaveVersion {
+    version: u32,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Serialize(ron::Error),
+    Parse(ron::error::SpannedError),
+    Version(u32),
+    Invalid(String),
+}
+
+impl Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "can't access the save file: {}", err),
+            SaveError::Serialize(err) => write!(f, "can't write the save: {}", err),
+            SaveError::Parse(err) => write!(f, "can't parse the save file: {}", err),
+            SaveError::Version(version) => write!(
+                f,
+                "the save file has version {}, but only version {} is supported",
+                version, SAVE_VERSION
+            ),
+            SaveError::Invalid(reason) => write!(f, "invalid save: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<std::io::Error> for SaveError {
+    fn from(err: std::io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+impl From<ron::Error> for SaveError {
+    fn from(err: ron::Error) -> Self {
+        SaveError::Serialize(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for SaveError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        SaveError::Parse(err)
+    }
+}
+
+impl SaveGame {
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        let save = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(path, save)?;
+
+        Ok(())
+    }
+
+    /// Reads the save from the RON file and checks it
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SaveError> {
+        let file = fs::read_to_string(path)?;
+
+        let SaveVersion { version } = ron::from_str(&file)?;
+        if version != SAVE_VERSION {
+            return Err(SaveError::Version(version));
+        }
+
+        let save: SaveGame = ron::from_str(&file)?;
+        save.validate()?;
+
+        Ok(save)
+    }
+
+    /// Checks, that all the saved references are pointing to the saved entities, so they can be
+    /// remapped
+    pub fn validate(&self) -> Result<(), SaveError> {
+        let has = |saved: &[Entity], entity: Entity| saved.contains(&entity);
+        let bases: Vec<Entity> = self.bases.iter().map(|base| base.entity).collect();
+        let corrals: Vec<Entity> = self.corrals.iter().map(|corral| corral.entity).collect();
+        let mut owners: Vec<Entity> = self.werewolves.iter().map(|w| w.entity).collect();
+        owners.push(self.player.entity);
+
+        if self.bases.iter().filter(|base| base.for_player).count() != 1 {
+            return Err(SaveError::Invalid(
+                "there must be exactly one player base".to_string(),
+            ));
+        }
+
+        if let Some(corral) = self.player.component.corral {
+            if !has(&corrals, corral) {
+                return Err(SaveError::Invalid(format!(
+                    "the corral {} of the player is not saved",
+                    corral
+                )));
+            }
+        }
+
+        for werewolf in self.werewolves.iter() {
+            if !has(&bases, werewolf.component.base) {
+                return Err(SaveError::Invalid(format!(
+                    "the base {} of the werewolf {} is not saved",
+                    werewolf.component.base, werewolf.entity
+                )));
+            }
+            if let Some(corral) = werewolf.component.corral {
+                if !has(&corrals, corral) {
+                    return Err(SaveError::Invalid(format!(
+                        "the corral {} of the werewolf {} is not saved",
+                        corral, werewolf.entity
+                    )));
+                }
+            }
+        }
+
+        for corral in self.corrals.iter() {
+            if let Some(owner) = corral.component.belongs_to {
+                if !has(&owners, owner) {
+                    return Err(SaveError::Invalid(format!(
+                        "the owner {} of the corral {} is not saved",
+                        owner, corral.entity
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Maps the entities from the save file to the new spawned ones
+struct SaveEntityMapper<'a>(&'a EntityHashMap<Entity>);
+
+impl EntityMapper for SaveEntityMapper<'_> {
+    fn map_entity(&mut self, entity: Entity) -> Entity {
+        // the save was validated, so there must be every entity
+        self.0.get(&entity).copied().unwrap_or(Entity::PLACEHOLDER)
+    }
+}
+
+pub fn save_load_keys(
+    input: Res<ButtonInput<KeyCode>>,
+    mut save_ev: EventWriter<EvSaveGame>,
+    mut load_ev: EventWriter<EvLoadGame>,
+    config: Res<GameConfig>,
+) {
+    if input.just_pressed(config.save.key_save) {
+        save_ev.send_default();
+    }
+    if input.just_pressed(config.save.key_load) {
+        load_ev.send_default();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn save_game(
+    mut save_ev: EventReader<EvSaveGame>,
+    player_q: Query<(Entity, &Transform, &Player, &Health)>,
+    bases_q: Query<(Entity, &Transform, &Base, Has<ForPlayer>)>,
+    corrals_q: Query<(Entity, &Transform, &ChickenCorral, Has<ForPlayer>)>,
+    werewolves_q: Query<(Entity, &Transform, &Werewolf)>,
+    chickens_q: Query<(Entity, &Transform, &Chicken)>,
+    obstacles_q: Query<(Entity, &Transform, &Obstacle)>,
+    layout: Res<MapLayout>,
+    explored: Res<Explored>,
+    game: Res<Game>,
+    player_res: Res<PlayerRes>,
+    config: Res<GameConfig>,
+) {
+    // saving more times in one frame gives the same file
+    if save_ev.read().count() == 0 {
+        return;
+    }
+    let Ok((player_ent, p_pos, player, health)) = player_q.get_single() else {
+        return;
+    };
+
+    let save = SaveGame {
+        version: SAVE_VERSION,
+        chicken_spawn_timer: game.chicken_spawn_timer.clone(),
+        round_time: game.round_time.clone(),
+        map: layout.clone(),
+        explored: explored.clone(),
+        inventory_chickens_amount: player_res.inventory_chickens_amount,
+        player: SavedEntity::new(player_ent, p_pos, true, player.clone()),
+        player_health: health.clone(),
+        bases: bases_q
+            .iter()
+            .map(|(ent, pos, base, for_player)| {
+                SavedEntity::new(ent, pos, for_player, base.clone())
+            })
+            .collect(),
+        corrals: corrals_q
+            .iter()
+            .map(|(ent, pos, corral, for_player)| {
+                SavedEntity::new(ent, pos, for_player, corral.clone())
+            })
+            .collect(),
+        werewolves: werewolves_q
+            .iter()
+            .map(|(ent, pos, werewolf)| SavedEntity::new(ent, pos, false, werewolf.clone()))
+            .collect(),
+        chickens: chickens_q
+            .iter()
+            .map(|(ent, pos, chicken)| SavedEntity::new(ent, pos, false, chicken.clone()))
+            .collect(),
+        obstacles: obstacles_q
+            .iter()
+            .map(|(ent, pos, obstacle)| SavedEntity::new(ent, pos, false, obstacle.clone()))
+            .collect(),
+    };
+
+    match save.write(&config.save.path) {
+        Ok(()) => info!("game saved to {}", config.save.path),
+        Err(err) => error!("{}: {}", config.save.path, err),
+    }
+}
+
+pub fn load_game(
+    mut commands: Commands,
+    mut load_ev: EventReader<EvLoadGame>,
+    in_round: Option<Res<State<InRound>>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    config: Res<GameConfig>,
+) {
+    if load_ev.read().count() == 0 {
+        return;
+    }
+
+    // if the save is broken, the game is going on
+    match SaveGame::load(&config.save.path) {
+        Ok(save) => {
+            commands.insert_resource(LoadedGame(save));
+            if in_round.is_none() {
+                next_state.set(GameState::Playing);
+            }
+        }
+        Err(err) => error!("{}: {}", config.save.path, err),
+    }
+}
+
+/// Replaces the entities of the round with the loaded ones
+#[allow(clippy::type_complexity)]
+pub fn apply_loaded_game(
+    mut commands: Commands,
+    loaded: Res<LoadedGame>,
+    round_entities_q: Query<
+        Entity,
+        Or<(
+            With<Player>,
+            With<Base>,
+            With<ChickenCorral>,
+            With<ChickenCorralWall>,
+            With<Werewolf>,
+            With<Chicken>,
+            With<Obstacle>,
+            With<GroundTile>,
+            With<FogTile>,
+        )>,
+    >,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut material: ResMut<Assets<ColorMaterial>>,
+    config: Res<GameConfig>,
+) {
+    let save = &loaded.0;
+    commands.remove_resource::<LoadedGame>();
+
+    // the ui of the round is staying
+    for entity in round_entities_q.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    spawn_ground_tiles(&mut commands, &save.map, &config);
+    commands.insert_resource(save.map.clone());
+    spawn_fog_tiles(&mut commands, &save.map, &config);
+    commands.insert_resource(save.explored.clone());
+
+    let mut entity_map = EntityHashMap::default();
+
+    let player_ent = spawn_player_entity(
+        &mut commands,
+        &mut meshes,
+        &mut material,
+        &config,
+        save.player.component.clone(),
+        save.player.translation.xy(),
+    );
+    entity_map.insert(save.player.entity, player_ent);
+
+    for base in save.bases.iter() {
+        let base_ent = spawn_base(
+            &mut commands,
+            &mut meshes,
+            &mut material,
+            &config,
+            base.component.clone(),
+            base.translation.xy(),
+            base.for_player,
+        );
+        entity_map.insert(base.entity, base_ent);
+    }
+
+    let mut corral_ents = Vec::new();
+    for corral in save.corrals.iter() {
+        let corral_ent = spawn_corral(
+            &mut commands,
+            &config,
+            corral.component.clone(),
+            corral.translation.xy(),
+            corral.for_player,
+        );
+        spawn_walls_of_corral(
+            &mut commands,
+            &config,
+            corral.translation,
+            &corral.component,
+        );
+        entity_map.insert(corral.entity, corral_ent);
+        corral_ents.push(corral_ent);
+    }
+
+    let mut werewolf_ents = Vec::new();
+    for werewolf in save.werewolves.iter() {
+        let werewolf_ent = spawn_werewolf(
+            &mut commands,
+            &mut meshes,
+            &mut material,
+            &config,
+            werewolf.component.clone(),
+            werewolf.translation.xy(),
+        );
+        entity_map.insert(werewolf.entity, werewolf_ent);
+        werewolf_ents.push(werewolf_ent);
+    }
+
+    for chicken in save.chickens.iter() {
+        commands.spawn((
+            ChickenBundle::new(chicken.component.clone(), chicken.translation, &config),
+            StateScoped(InRound),
+        ));
+    }
+
+    for obstacle in save.obstacles.iter() {
+        spawn_obstacle(
+            &mut commands,
+            &mut meshes,
+            &mut material,
+            &config,
+            obstacle.component.clone(),
+            obstacle.translation.xy(),
+        );
+    }
+
+    // the corrals and their owners are referencing each other, so the references can be mapped
+    // only after everything is spawned
+    let mut mapper = SaveEntityMapper(&entity_map);
+
+    let mut player = save.player.component.clone();
+    player.map_entities(&mut mapper);
+    // the keys could be changed in the config after the saving
+    player.set_keys(&config);
+    commands
+        .entity(player_ent)
+        .insert((player, save.player_health.clone()));
+
+    for (corral, corral_ent) in save.corrals.iter().zip(corral_ents) {
+        let mut corral = corral.component.clone();
+        corral.map_entities(&mut mapper);
+        commands.entity(corral_ent).insert(corral);
+    }
+
+    for (werewolf, werewolf_ent) in save.werewolves.iter().zip(werewolf_ents) {
+        let mut werewolf = werewolf.component.clone();
+        werewolf.map_entities(&mut mapper);
+        commands.entity(werewolf_ent).insert(werewolf);
+    }
+
+    commands.insert_resource(Game {
+        chicken_spawn_timer: save.chicken_spawn_timer.clone(),
+        lvlup_screen_opened: false,
+        round_time: save.round_time.clone(),
+    });
+    // the catchable chicken is found again by the player
+    commands.insert_resource(PlayerRes {
+        inventory_chickens_amount: save.inventory_chickens_amount,
+        catchable_chicken: None,
+    });
+
+    info!("game loaded from {}", config.save.path);
+}
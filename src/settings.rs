@@ -1,9 +1,18 @@
 use bevy::{color::Color, prelude::KeyCode};
 
+// this are the default values of the GameConfig, the real values are loaded from the config file
+// (see config.rs)
+
 /// Main section
 pub const CHICKEN_SPAWN_DELTA: f32 = 3.0;
-// pub const MAP_SIZE: f32 = 1000.0;
+// round objectives, None means that there is no such objective
+pub const ROUND_TARGET_CHICKENS: Option<usize> = Some(200);
+pub const ROUND_TIME_LIMIT: Option<f32> = Some(600.0);
+// if the player base must have more chickens than every werewolf base after the time limit
+pub const ROUND_OUTCOLLECT_WEREWOLVES: bool = true;
 pub const TEXT_Z: f32 = 10.;
+// the seed of the random numbers, None means that every round gets a random one
+pub const SEED: Option<u64> = None;
 
 /// Camera section
 pub const MAX_CAMERA_SPEED: f32 = 50.0;
@@ -23,12 +32,19 @@ pub const PLAYER_CATCHING_RADIUS: f32 = 100.0;
 pub const PLAYER_MAX_INVENTORY_SPACE: usize = 5;
 pub const PLAYER_SIGHT_DISTANCE: f32 = 300.0;
 pub const PLAYER_DEFAULT_CATCHING_RADIUS_MULTIPLIER: f32 = 1.0;
+pub const PLAYER_DEFAULT_SIGHT_MULTIPLIER: f32 = 1.0;
 pub const PLAYER_DEFAULT_SPEED_MULTIPLIER: f32 = 1.0;
 pub const PLAYER_LVLUP_SPEED: f32 = 0.1;
 pub const PLAYER_LVLUP_CATCHING_RADIUS: f32 = 0.1;
+pub const PLAYER_LVLUP_SIGHT: f32 = 0.1;
 pub const BASE_PLAYER_CATCHING_RADIUS_COLOR: Color = Color::linear_rgb(0.0, 0.5, 0.5);
 pub const PLAYER_LVLUP_SPEED_PRICE: usize = 5;
 pub const PLAYER_LVLUP_CATCHING_RADIUS_PRICE: usize = 5;
+pub const PLAYER_LVLUP_SIGHT_PRICE: usize = 5;
+pub const PLAYER_MAX_HEALTH: f32 = 100.0;
+// how long the player is knocked out, before it is respawned in the base
+pub const PLAYER_KNOCKOUT_DELTA: f32 = 5.0;
+pub const PLAYER_LOOT_DELTA: f32 = 1.0;
 // control
 pub const PLAYER_KEY_UP: KeyCode = KeyCode::KeyW;
 pub const PLAYER_KEY_DOWN: KeyCode = KeyCode::KeyS;
@@ -37,6 +53,8 @@ pub const PLAYER_KEY_RIGHT: KeyCode = KeyCode::KeyD;
 pub const PLAYER_KEY_CATCH: KeyCode = KeyCode::KeyE;
 pub const PLAYER_KEY_GIVE: KeyCode = KeyCode::KeyQ;
 pub const PLAYER_KEY_UPGRADE: KeyCode = KeyCode::KeyC;
+pub const PLAYER_KEY_LOOT: KeyCode = KeyCode::KeyR;
+pub const PLAYER_KEY_PAUSE: KeyCode = KeyCode::Escape;
 
 /// werewolf section
 pub const WEREWOLF_COLOR: Color = Color::linear_rgb(0.5, 0.0, 0.0);
@@ -49,6 +67,16 @@ pub const WEREWOLF_BEHAVIOUR_CHANGE_DELTA: f32 = 2.0;
 pub const WEREWOLF_MIN_DISTANCE_TO_CORRAL: f32 = 100.0;
 pub const WEREWOLF_MAX_INVENTORY_SPACE: usize = 3;
 pub const WEREWOLF_CATCHING_RADIUS_COLOR: Color = Color::linear_rgb(0.5, 0.0, 0.0);
+// the werewolf is hunting the player, if it is nearer than this
+pub const WEREWOLF_SIGHT_DISTANCE: f32 = 250.0;
+pub const WEREWOLF_HUNT_SPEED: f32 = 90.0;
+pub const WEREWOLF_ATTACK_RADIUS: f32 = 50.0;
+pub const WEREWOLF_ATTACK_DAMAGE: f32 = 25.0;
+pub const WEREWOLF_ATTACK_DELTA: f32 = 1.0;
+pub const WEREWOLF_RAID_TRY_DELTA: f32 = 10.0;
+pub const WEREWOLF_RAID_CHANCE: f64 = 0.3;
+pub const WEREWOLF_NAV_CELL_SIZE: f32 = 25.0;
+pub const WEREWOLF_REPLAN_DELAY: f32 = 1.0;
 /// base section
 pub const BASE_CATCHING_RADIUS: f32 = 100.0;
 pub const BASE_SIZE: f32 = 50.0;
@@ -63,6 +91,25 @@ pub const CHICKEN_SIZE: f32 = 30.0;
 pub const CHICKEN_BEHAVIOUR_CHANGE_DELTA: f32 = 5.0;
 pub const CHICKEN_COLOR: Color = Color::linear_rgb(0.0, 0.0, 0.5);
 pub const CHICKEN_Z: f32 = 3.0;
+pub const CHICKEN_AGGRESSIVE_CHANCE: f64 = 0.1;
+pub const CHICKEN_AGGRESSIVE_SPEED: f32 = 90.0;
+pub const CHICKEN_AGGRESSIVE_COLOR: Color = Color::linear_rgb(0.8, 0.4, 0.0);
+pub const CHICKEN_AGGRESSIVE_GIVE_UP_DISTANCE: f32 = 400.0;
+pub const CHICKEN_STRUGGLE_PRESSES: usize = 5;
+pub const CHICKEN_PECK_DISTANCE: f32 = 45.0;
+pub const CHICKEN_PECK_DELTA: f32 = 1.0;
+pub const CHICKEN_PECK_SLOWDOWN: f32 = 0.5;
+pub const CHICKEN_PECK_SLOW_DELTA: f32 = 2.0;
+pub const CHICKEN_FLEE_RADIUS: f32 = 200.0;
+pub const CHICKEN_PANIC_SPEED: f32 = 90.0;
+pub const CHICKEN_PANIC_DELTA: f32 = 2.0;
+pub const CHICKEN_PANIC_COOLDOWN: f32 = 3.0;
+pub const FLOCKING_NEIGHBOUR_RADIUS: f32 = 120.0;
+pub const FLOCKING_SEPARATION_DISTANCE: f32 = 40.0;
+pub const FLOCKING_SEPARATION: f32 = 60.0;
+pub const FLOCKING_ALIGNMENT: f32 = 0.3;
+pub const FLOCKING_COHESION: f32 = 0.2;
+pub const FLOCKING_MAX_SPEED: f32 = 100.0;
 
 /// Corral section
 pub const DEFAULT_CORRAL_LENGTH: usize = 8;
@@ -72,6 +119,7 @@ pub const CORRAL_WALL_HEIGTH: f32 = 20.0;
 // legth must be langet than heigth
 pub const CORRAL_WALL_LENGTH: f32 = 50.0;
 pub const CORRAL_WALL_COLOR: Color = Color::linear_rgba(0.1, 0.1, 0.1, 0.1);
+pub const CORRAL_GATE_COLOR: Color = Color::linear_rgba(0.4, 0.25, 0.1, 0.3);
 
 // spawning things
 pub const P_CORRAL_DISTANCE_FROM_CENTER: f32 = 200.0;
@@ -82,3 +130,58 @@ pub const WEREWOLF_DISTANCE_TO_CENTER: f32 = 500.0;
 pub const WEREWOLF_AMOUNT: usize = 4;
 // preventing spawning the entities in the simillar parts of the circle
 pub const ANGLE_MARGIN: f32 = 15.;
+
+/// Map section
+// the hand made map, None means that the map is generated
+pub const MAP_FILE: Option<&str> = None;
+// the side length of the square arena
+pub const MAP_SIZE: f32 = 3000.0;
+pub const MAP_TILE_SIZE: f32 = 100.0;
+pub const MAP_GROUND_Z: f32 = 0.0;
+pub const MAP_BIOME_AMOUNT: usize = 12;
+pub const GRASS_COLOR: Color = Color::linear_rgb(0.05, 0.2, 0.05);
+pub const GRASS_SPEED_MULTIPLIER: f32 = 1.0;
+pub const GRASS_WEIGHT: u32 = 3;
+pub const MUD_COLOR: Color = Color::linear_rgb(0.15, 0.1, 0.05);
+pub const MUD_SPEED_MULTIPLIER: f32 = 0.5;
+pub const MUD_WEIGHT: u32 = 1;
+pub const FOREST_COLOR: Color = Color::linear_rgb(0.02, 0.1, 0.02);
+pub const FOREST_SPEED_MULTIPLIER: f32 = 0.8;
+pub const FOREST_WEIGHT: u32 = 1;
+pub const MAP_OBSTACLE_Z: f32 = 6.0;
+pub const MAP_CLEARANCE: f32 = 50.0;
+pub const MAP_BUSH_REVEAL_DISTANCE: f32 = 150.0;
+pub const MAP_TREE_AMOUNT: usize = 40;
+pub const MAP_TREE_RADIUS: f32 = 30.0;
+pub const MAP_TREE_COLOR: Color = Color::linear_rgb(0.0, 0.25, 0.0);
+pub const MAP_ROCK_AMOUNT: usize = 20;
+pub const MAP_ROCK_RADIUS: f32 = 25.0;
+pub const MAP_ROCK_COLOR: Color = Color::linear_rgb(0.3, 0.3, 0.3);
+pub const MAP_BUSH_AMOUNT: usize = 25;
+pub const MAP_BUSH_RADIUS: f32 = 45.0;
+pub const MAP_BUSH_COLOR: Color = Color::linear_rgba(0.1, 0.5, 0.1, 0.9);
+
+/// Minimap section
+pub const MINIMAP_SHOWN: bool = true;
+pub const MINIMAP_KEY_TOGGLE: KeyCode = KeyCode::KeyM;
+pub const MINIMAP_SIZE: f32 = 200.0;
+pub const MINIMAP_MARGIN: f32 = 10.0;
+pub const MINIMAP_BACKGROUND_COLOR: Color = Color::linear_rgb(0.0, 0.0, 0.0);
+pub const MINIMAP_ICON_SIZE: f32 = 100.0;
+pub const MINIMAP_TEXT_SIZE: f32 = 150.0;
+pub const MINIMAP_PLAYER_COLOR: Color = Color::linear_rgb(1.0, 1.0, 1.0);
+
+/// Fog section
+pub const FOG_Z: f32 = 20.0;
+pub const FOG_COLOR: Color = Color::linear_rgb(0.0, 0.0, 0.0);
+pub const FOG_EXPLORED_ALPHA: f32 = 0.6;
+
+/// Save section
+pub const SAVE_PATH: &str = "savegame.ron";
+pub const SAVE_KEY: KeyCode = KeyCode::F5;
+pub const LOAD_KEY: KeyCode = KeyCode::F9;
+
+/// Editor section
+pub const EDITOR_KEY_TOGGLE: KeyCode = KeyCode::F2;
+pub const EDITOR_CAMERA_SPEED: f32 = 600.0;
+pub const EDITOR_DEFAULT_PATH: &str = "maps/custom.ron";
@@ -1,12 +1,15 @@
 use crate::{
-    base::{BaseBundle, BaseCatchingRadius, BaseText, BelongToBase},
+    base::{Base, BaseBundle, BaseCatchingRadius, BaseText, BelongToBase},
     chicken::ChickenBundle,
     chicken_corral::{ChickenCorral, ChickenCorralWall, WallType},
-    misc::get_random_dir,
-    player::{ForPlayer, PlayerBundle, PlayerCatchingRadius},
-    settings::*,
+    config::GameConfig,
+    map::{CorralOwner, MapLayout},
+    player::{ForPlayer, Player, PlayerBundle, PlayerCatchingRadius},
+    rng::GameRng,
+    state::InRound,
     werewolf::{
-        BelongToWerewolf, ForWerewolf, WerewolfBundle, WerewolfCatchingRadius, WerewolfText,
+        BelongToWerewolf, ForWerewolf, Werewolf, WerewolfBundle, WerewolfCatchingRadius,
+        WerewolfText,
     },
     Game,
 };
@@ -17,68 +20,236 @@ use crate::{
 /// The idea is, that every entity has its own circle for spawning. So it is easier to prevent
 /// overlapping and to get a little bit randomness in spawning process.
 ///
-/// The Rings:
+/// The Rings (the distances are in the spawning section of the GameConfig):
 /// Player - center of the map
-/// Player Base - BASE_DISTANCCE_FROM_ENTITIES away from player
-/// Player Corral - P_CORRAL_DISTANCE_FROM_CENTER away from center
-/// Werewolf - WEREWOLF_DISTANCE_FROM_CENTER away from center
-/// Werewolf Base - BASE_DISTANCCE_FROM_ENTITIES away from werewolf
-/// Werewolf Corrals - W_CORRAL_DISTANCE_FROM_CENTER away from center
+/// Player Base - base_distance_from_entity away from player
+/// Player Corral - p_corral_distance_from_center away from center
+/// Werewolf - werewolf_distance_to_center away from center
+/// Werewolf Base - base_distance_from_entity away from werewolf
+/// Werewolf Corrals - w_corral_distance_from_center away from center
+///
+/// The places on the rings are chosen by the MapLayout (see map.rs), which is moving them further
+/// out on the ring, when they would overlap. The obstacles are placed between the rings after all
+/// of this is spawned.
+///
+/// The systems are only taking the places from the layout, the entities itself are spawned by the
+/// spawn_* functions, which are used by the loading of the saved game too
 use bevy::{
     prelude::*,
     sprite::{MaterialMesh2dBundle, Mesh2dHandle},
 };
-use rand::Rng;
+
+/// The thin circle, which is showing the catching radius of the entity
+pub fn radius_mesh(meshes: &mut Assets<Mesh>, radius: f32) -> Mesh2dHandle {
+    Mesh2dHandle(meshes.add(Annulus::new(radius - 1., radius)))
+}
 
 // we spawning player in the center of map (0.0,0.0)
 pub fn spawn_player(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut material: ResMut<Assets<ColorMaterial>>,
+    layout: Res<MapLayout>,
+    config: Res<GameConfig>,
+) {
+    spawn_player_entity(
+        &mut commands,
+        &mut meshes,
+        &mut material,
+        &config,
+        Player::from_config(&config),
+        layout.player,
+    );
+}
+
+// then spawning the base of it in the distance of 100 + PLayer_SIZE from player
+pub fn spawn_player_base(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut material: ResMut<Assets<ColorMaterial>>,
+    layout: Res<MapLayout>,
+    config: Res<GameConfig>,
+) {
+    spawn_base(
+        &mut commands,
+        &mut meshes,
+        &mut material,
+        &config,
+        Base::from_config(&config),
+        layout.player_base,
+        true,
+    );
+}
+
+pub fn spawn_player_corral(
+    mut commands: Commands,
+    layout: Res<MapLayout>,
+    config: Res<GameConfig>,
+) {
+    for corral in layout.corrals_of(CorralOwner::Player) {
+        spawn_corral(
+            &mut commands,
+            &config,
+            corral.component(None),
+            corral.pos,
+            true,
+        );
+    }
+}
+
+// for werewolfs we spawning them in the part of the next spawning circle with distance
+// werewolf_distance_to_center from center
+pub fn spawn_werewolf_with_base_and_corrals(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut material: ResMut<Assets<ColorMaterial>>,
+    layout: Res<MapLayout>,
+    config: Res<GameConfig>,
+) {
+    for (i, places) in layout.werewolves.iter().enumerate() {
+        let base_ent = spawn_base(
+            &mut commands,
+            &mut meshes,
+            &mut material,
+            &config,
+            Base::from_config(&config),
+            places.base,
+            false,
+        );
+
+        // every werewolf has exactly one corral (see map_file.rs)
+        let corral = layout.corrals_of(CorralOwner::Werewolf(i)).next();
+        let corral_ent = corral.map(|corral| {
+            spawn_corral(
+                &mut commands,
+                &config,
+                corral.component(None),
+                corral.pos,
+                false,
+            )
+        });
+
+        let mut werewolf = Werewolf::new(base_ent, places.werewolf, &config);
+        werewolf.corral = corral_ent;
+        werewolf.corral_pos = corral.map(|corral| corral.pos);
+        let werewolf_ent = spawn_werewolf(
+            &mut commands,
+            &mut meshes,
+            &mut material,
+            &config,
+            werewolf,
+            places.werewolf,
+        );
+
+        // the corral can know its owner only after the werewolf is spawned
+        if let (Some(corral), Some(corral_ent)) = (corral, corral_ent) {
+            commands
+                .entity(corral_ent)
+                .insert(corral.component(Some(werewolf_ent)));
+        }
+    }
+}
+
+/// The corrals, which are not belonging to anybody
+pub fn spawn_free_corrals(mut commands: Commands, layout: Res<MapLayout>, config: Res<GameConfig>) {
+    for corral in layout.corrals_of(CorralOwner::Nobody) {
+        spawn_corral(
+            &mut commands,
+            &config,
+            corral.component(None),
+            corral.pos,
+            false,
+        );
+    }
+}
+
+pub fn spawn_chicken_in_corrals(
+    mut commands: Commands,
+    mut game: ResMut<Game>,
+    time: Res<Time>,
+    corral_q: Query<(&Transform, &ChickenCorral)>,
+    mut rng: ResMut<GameRng>,
+    config: Res<GameConfig>,
 ) {
+    game.chicken_spawn_timer.tick(time.delta());
+
+    if game.chicken_spawn_timer.finished() {
+        corral_q.iter().for_each(|(c_pos, corral)| {
+            // pos randomiser in corral is in the bundle itself
+            commands.spawn((
+                ChickenBundle::default_in_corral(c_pos.translation, corral, &config, &mut rng),
+                StateScoped(InRound),
+            ));
+        });
+    }
+}
+
+pub fn spawn_corral_walls(
+    mut commands: Commands,
+    corral_q: Query<(&Transform, &ChickenCorral)>,
+    config: Res<GameConfig>,
+) {
+    for (c_pos, corral) in corral_q.iter() {
+        spawn_walls_of_corral(&mut commands, &config, c_pos.translation, corral);
+    }
+}
+
+/// Spawns the player with its catching radius
+pub fn spawn_player_entity(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    material: &mut Assets<ColorMaterial>,
+    config: &GameConfig,
+    player: Player,
+    pos: Vec2,
+) -> Entity {
+    let catching_radius = config.player.catching_radius * player.catching_radius_multiplier;
+
     commands
-        .spawn(PlayerBundle::default())
+        .spawn((PlayerBundle::new(player, pos, config), StateScoped(InRound)))
         .with_children(|parent| {
             parent.spawn((
                 MaterialMesh2dBundle {
-                    mesh: Mesh2dHandle(meshes.add(Annulus::new(
-                        PLAYER_CATCHING_RADIUS - 1.,
-                        PLAYER_CATCHING_RADIUS,
-                    ))),
-                    material: material.add(BASE_PLAYER_CATCHING_RADIUS_COLOR),
+                    mesh: radius_mesh(meshes, catching_radius),
+                    material: material.add(config.player.catching_radius_color),
                     ..Default::default()
                 },
                 PlayerCatchingRadius,
             ));
-        });
+        })
+        .id()
 }
 
-// then spawning the base of it in the distance of 100 + PLayer_SIZE from player
-pub fn spawn_player_base(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut material: ResMut<Assets<ColorMaterial>>,
-) {
-    let base_ent = commands
-        .spawn((
-            BaseBundle::default_on_point(
-                get_random_dir() * (BASE_DISTANCE_FROM_ENTITY + PLAYER_SIZE),
-            ),
-            ForPlayer,
-        ))
+/// Spawns the base with its catching radius and the text with the chickens amount
+pub fn spawn_base(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    material: &mut Assets<ColorMaterial>,
+    config: &GameConfig,
+    base: Base,
+    pos: Vec2,
+    for_player: bool,
+) -> Entity {
+    let mut base_cmds = commands.spawn((BaseBundle::new(base, pos, config), StateScoped(InRound)));
+    if for_player {
+        base_cmds.insert(ForPlayer);
+    } else {
+        base_cmds.insert(ForWerewolf);
+    }
+
+    let base_ent = base_cmds
         .with_children(|parent| {
-            parent.spawn((
+            let mut radius_cmds = parent.spawn((
                 MaterialMesh2dBundle {
-                    mesh: Mesh2dHandle(meshes.add(Annulus::new(
-                        BASE_CATCHING_RADIUS - 1.,
-                        BASE_CATCHING_RADIUS,
-                    ))),
-                    material: material.add(BASE_CATCHING_RADIUS_COLOR),
+                    mesh: radius_mesh(meshes, config.base.catching_radius),
+                    material: material.add(config.base.catching_radius_color),
                     ..Default::default()
                 },
-                BaseCatchingRadius::default(),
-                ForPlayer,
+                BaseCatchingRadius,
             ));
+            if for_player {
+                radius_cmds.insert(ForPlayer);
+            }
         })
         .id();
 
@@ -87,7 +258,7 @@ pub fn spawn_player_base(
         .spawn(BaseText {
             base: BelongToBase { base: base_ent },
             text_bundle: Text2dBundle {
-                transform: Transform::from_translation(Vec3::new(0., 0., TEXT_Z)),
+                transform: Transform::from_translation(Vec3::new(0., 0., config.text_z)),
                 text: Text::from_section("0", TextStyle::default()),
                 ..Default::default()
             },
@@ -95,208 +266,153 @@ pub fn spawn_player_base(
         .id();
 
     commands.entity(base_ent).push_children(&[text_ent]);
-}
 
-pub fn spawn_player_corral(mut commands: Commands) {
-    commands.spawn((
-        ChickenCorral {
-            belongs_to: None,
-            length: DEFAULT_CORRAL_LENGTH,
-            heigth: DEFAULT_CORRAL_HEIGTH,
-        },
-        Transform::from_translation(
-            (get_random_dir() * P_CORRAL_DISTANCE_FROM_CENTER).extend(CORRAL_Z),
-        ),
-        ForPlayer,
-    ));
+    base_ent
 }
 
-// for werewolfs we spawning them in the part of the next spawning circle with distance
-// WEREWOLF_CIRCLE_SPAWN_DISTANCE from center
-pub fn spawn_werewolf_with_base_and_corrals(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut material: ResMut<Assets<ColorMaterial>>,
-) {
-    let angle_step = 360.0 / WEREWOLF_AMOUNT as f32;
-
-    for i in 0..WEREWOLF_AMOUNT {
-        // getting random angle in our part of the werewolf circle
-        let angle = rand::thread_rng()
-            .gen_range(
-                (angle_step + ANGLE_MARGIN / 2.) * i as f32
-                    ..(angle_step - ANGLE_MARGIN / 2.) * (i + 1) as f32,
-            )
-            .to_radians();
-        let spawn_dir = Vec2::new(angle.sin(), angle.cos());
-
-        let werewolf_base_spawnpoint = (spawn_dir * WEREWOLF_DISTANCE_TO_CENTER)
-            + (get_random_dir() * BASE_DISTANCE_FROM_ENTITY);
-
-        let base_ent = commands
-            .spawn((
-                BaseBundle::default_on_point(werewolf_base_spawnpoint),
-                ForWerewolf,
-            ))
-            .with_children(|parent| {
-                parent.spawn((
-                    MaterialMesh2dBundle {
-                        mesh: Mesh2dHandle(meshes.add(Annulus::new(
-                            BASE_CATCHING_RADIUS - 1.,
-                            BASE_CATCHING_RADIUS,
-                        ))),
-                        material: material.add(BASE_CATCHING_RADIUS_COLOR),
-                        ..Default::default()
-                    },
-                    BaseCatchingRadius::default(),
-                ));
-            })
-            .id();
-
-        let werewolf_ent = commands
-            .spawn(WerewolfBundle::default_on_point_with_base(
-                spawn_dir * WEREWOLF_DISTANCE_TO_CENTER,
-                base_ent,
-            ))
-            .with_children(|parent| {
-                parent.spawn((
-                    MaterialMesh2dBundle {
-                        mesh: Mesh2dHandle(meshes.add(Annulus::new(
-                            WEREWOLF_CATCHING_RADIUS - 1.,
-                            WEREWOLF_CATCHING_RADIUS,
-                        ))),
-                        material: material.add(WEREWOLF_CATCHING_RADIUS_COLOR),
-                        ..Default::default()
-                    },
-                    WerewolfCatchingRadius,
-                ));
-            })
-            .id();
-
-        // spawning chickens amount on base
-        let text_ent = commands
-            .spawn(BaseText {
-                base: BelongToBase { base: base_ent },
-                text_bundle: Text2dBundle {
-                    transform: Transform::from_translation(Vec3::new(0., 0., TEXT_Z)),
-                    text: Text::from_section("0", TextStyle::default()),
+/// Spawns the werewolf with its catching radius and the text with the chickens amount
+pub fn spawn_werewolf(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    material: &mut Assets<ColorMaterial>,
+    config: &GameConfig,
+    werewolf: Werewolf,
+    pos: Vec2,
+) -> Entity {
+    let werewolf_ent = commands
+        .spawn((
+            WerewolfBundle::new(werewolf, pos, config),
+            StateScoped(InRound),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                MaterialMesh2dBundle {
+                    mesh: radius_mesh(meshes, config.werewolf.catching_radius),
+                    material: material.add(config.werewolf.catching_radius_color),
                     ..Default::default()
                 },
-            })
-            .id();
-
-        commands.entity(base_ent).push_children(&[text_ent]);
+                WerewolfCatchingRadius,
+            ));
+        })
+        .id();
 
-        commands.spawn((
-            ChickenCorral {
-                belongs_to: None,
-                length: DEFAULT_CORRAL_LENGTH,
-                heigth: DEFAULT_CORRAL_HEIGTH,
+    let text_ent = commands
+        .spawn(WerewolfText {
+            werewolf: BelongToWerewolf {
+                werewolf: werewolf_ent,
             },
-            Transform::from_translation(
-                (spawn_dir * W_CORRAL_DISTANCE_FROM_CENTER).extend(CORRAL_Z),
-            ),
-        ));
+            text_bundle: Text2dBundle {
+                transform: Transform::from_translation(Vec3::new(0., 0., config.text_z)),
+                text: Text::from_section("0", TextStyle::default()),
+                ..Default::default()
+            },
+        })
+        .id();
 
-        let text_ent = commands
-            .spawn(WerewolfText {
-                werewolf: BelongToWerewolf {
-                    werewolf: werewolf_ent,
-                },
-                text_bundle: Text2dBundle {
-                    transform: Transform::from_translation(Vec3::new(0., 0., TEXT_Z)),
-                    text: Text::from_section("0", TextStyle::default()),
-                    ..Default::default()
-                },
-            })
-            .id();
+    commands.entity(werewolf_ent).push_children(&[text_ent]);
 
-        commands.entity(werewolf_ent).push_children(&[text_ent]);
+    werewolf_ent
+}
+
+/// Spawns only the corral itself, the walls are spawned by `spawn_walls_of_corral`
+pub fn spawn_corral(
+    commands: &mut Commands,
+    config: &GameConfig,
+    corral: ChickenCorral,
+    pos: Vec2,
+    for_player: bool,
+) -> Entity {
+    let mut corral_cmds = commands.spawn((
+        corral,
+        Transform::from_translation(pos.extend(config.corral.z)),
+        StateScoped(InRound),
+    ));
+    if for_player {
+        corral_cmds.insert(ForPlayer);
     }
+
+    corral_cmds.id()
 }
 
-pub fn spawn_chicken_in_corrals(
-    mut commands: Commands,
-    mut game: ResMut<Game>,
-    time: Res<Time>,
-    corral_q: Query<(&Transform, &ChickenCorral)>,
+pub fn spawn_walls_of_corral(
+    commands: &mut Commands,
+    config: &GameConfig,
+    c_pos: Vec3,
+    corral: &ChickenCorral,
 ) {
-    game.chicken_spawn_timer.tick(time.delta());
+    let wall_length = config.corral.wall_length;
+    let wall_heigth = config.corral.wall_heigth;
 
-    if game.chicken_spawn_timer.finished() {
-        corral_q.iter().for_each(|(c_pos, corral)| {
-            // pos randomiser in corral is in the bundle itself
-            commands.spawn(ChickenBundle::default_in_corral(c_pos.translation, corral));
-        });
-    }
-}
+    // making the first wall of the corral be on the top left of the corral, when the
+    // spawnpoint is centered in the corral
+    let mut current_wall_center = c_pos
+        + Vec3::new(
+            -wall_length * corral.length as f32 / 2.,
+            wall_length * corral.heigth as f32 / 2.,
+            0.,
+        );
 
-pub fn spawn_corral_walls(mut commands: Commands, corral_q: Query<(&Transform, &ChickenCorral)>) {
-    for (c_pos, corral) in corral_q.iter() {
-        // making the first wall of the corral be on the top left of the corral, when the
-        // spawnpoint is centered in the corral
-        let mut current_wall_center = c_pos.translation
-            + Vec3::new(
-                -CORRAL_WALL_LENGTH * DEFAULT_CORRAL_LENGTH as f32 / 2.,
-                CORRAL_WALL_LENGTH * DEFAULT_CORRAL_LENGTH as f32 / 2.,
-                0.,
-            );
-
-        // we need to make 4 sides of the corral
-        for turning_i in 0..4 as usize {
-            // if it the 0 or 2 step, then we moving vertically, else horizontaly
-            let (steps, dir, wall_size) = match turning_i {
-                0 => (
-                    corral.length,
-                    Vec3::new(1., 0., 0.),
-                    Vec2::new(CORRAL_WALL_LENGTH, CORRAL_WALL_HEIGTH),
-                ),
-                1 => (
-                    corral.heigth,
-                    Vec3::new(0., -1., 0.),
-                    Vec2::new(CORRAL_WALL_HEIGTH, CORRAL_WALL_LENGTH),
-                ),
-                2 => (
-                    corral.length,
-                    Vec3::new(-1., 0., 0.),
-                    Vec2::new(CORRAL_WALL_LENGTH, CORRAL_WALL_HEIGTH),
-                ),
-                3 => (
-                    corral.heigth,
-                    Vec3::new(0., 1., 0.),
-                    Vec2::new(CORRAL_WALL_HEIGTH, CORRAL_WALL_LENGTH),
+    // we need to make 4 sides of the corral
+    for turning_i in 0..4 as usize {
+        // if it the 0 or 2 step, then we moving vertically, else horizontaly
+        let (steps, dir, wall_size) = match turning_i {
+            0 => (
+                corral.length,
+                Vec3::new(1., 0., 0.),
+                Vec2::new(wall_length, wall_heigth),
+            ),
+            1 => (
+                corral.heigth,
+                Vec3::new(0., -1., 0.),
+                Vec2::new(wall_heigth, wall_length),
+            ),
+            2 => (
+                corral.length,
+                Vec3::new(-1., 0., 0.),
+                Vec2::new(wall_length, wall_heigth),
+            ),
+            3 => (
+                corral.heigth,
+                Vec3::new(0., 1., 0.),
+                Vec2::new(wall_heigth, wall_length),
+            ),
+            _ => unreachable!(),
+        };
+
+        for i in 0..steps {
+            // if it the first wall, then it will be the corner, the gate is in the middle of the
+            // bottom side, it is wide enough for the player
+            let wall_type = match i {
+                0 => WallType::Corner,
+                _ if turning_i == 2 && i.abs_diff(steps / 2) <= 1 => WallType::Gate,
+                _ => WallType::Edge,
+            };
+
+            let (wall_size, color) = match wall_type {
+                WallType::Corner => (
+                    Vec2::new(wall_length, wall_length),
+                    config.corral.wall_color,
                 ),
-                _ => unreachable!(),
+                WallType::Edge => (wall_size, config.corral.wall_color),
+                WallType::Gate => (wall_size, config.corral.gate_color),
             };
 
-            for i in 0..steps {
-                // if it the first wall, then it will be the corner
-                let wall_type = match i {
-                    0 => WallType::Corner,
-                    _ => WallType::Edge,
-                };
-
-                let wall_size = match wall_type {
-                    WallType::Corner => Vec2::new(CORRAL_WALL_LENGTH, CORRAL_WALL_LENGTH),
-                    WallType::Edge => wall_size,
-                };
-
-                commands.spawn((
-                    SpriteBundle {
-                        transform: Transform::from_translation(current_wall_center),
-                        sprite: Sprite {
-                            color: CORRAL_WALL_COLOR,
-                            custom_size: Some(wall_size),
-                            ..Default::default()
-                        },
+            commands.spawn((
+                SpriteBundle {
+                    transform: Transform::from_translation(current_wall_center),
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(wall_size),
                         ..Default::default()
                     },
-                    ChickenCorralWall { wall_type },
-                ));
+                    ..Default::default()
+                },
+                ChickenCorralWall { wall_type },
+                StateScoped(InRound),
+            ));
 
-                // moving the center of the next wall
-                current_wall_center += dir * CORRAL_WALL_LENGTH;
-            }
+            // moving the center of the next wall
+            current_wall_center += dir * wall_length;
         }
     }
 }
@@ -0,0 +1,92 @@
+use bevy::prelude::*;
+
+use crate::{config::GameConfig, Game, PlayerRes};
+
+/// All the other game plugins are depending on this one, their systems are running only in the
+/// right states
+pub struct GameStatePlugin;
+
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>();
+
+        app.init_state::<GameState>();
+        app.add_computed_state::<InRound>();
+        // everything, which is spawned for the round, is marked with StateScoped(InRound) and
+        // despawned after the round
+        app.enable_state_scoped_entities::<InRound>();
+
+        app.configure_sets(
+            OnEnter(InRound),
+            (SpawnSet::Entities, SpawnSet::Assign, SpawnSet::Decorate).chain(),
+        );
+        app.add_systems(OnEnter(InRound), reset_round_resources);
+
+        app.add_systems(
+            Update,
+            toggle_pause.run_if(in_state(GameState::Playing).or_else(in_state(GameState::Paused))),
+        );
+    }
+}
+
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GameState {
+    #[default]
+    MainMenu,
+    Playing,
+    Paused,
+    GameOver,
+    /// the level editor, see editor.rs
+    Editor,
+}
+
+/// The round is going on (the game can be paused), the entities of the round are spawned on enter
+/// and despawned on exit of this state, so pausing the game is not respawning everything
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InRound;
+
+impl ComputedStates for InRound {
+    type SourceStates = GameState;
+
+    fn compute(sources: GameState) -> Option<Self> {
+        match sources {
+            GameState::Playing | GameState::Paused => Some(InRound),
+            GameState::MainMenu | GameState::GameOver | GameState::Editor => None,
+        }
+    }
+}
+
+/// The systems, which are spawning the round on `OnEnter(InRound)`, are split in this sets, so
+/// the plugins can spawn their things independently and link them together afterwards
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SpawnSet {
+    /// spawning the player, bases, werewolves and corrals
+    Entities,
+    /// assigning the spawned entities to each other (corral owners and so on)
+    Assign,
+    /// spawning the things, that depend on the assigned entities (corral walls)
+    Decorate,
+}
+
+// the resources are staying between the rounds, so they must be cleaned
+fn reset_round_resources(mut commands: Commands, config: Res<GameConfig>) {
+    commands.insert_resource(Game::from_config(&config));
+    commands.insert_resource(PlayerRes::default());
+}
+
+pub fn toggle_pause(
+    input: Res<ButtonInput<KeyCode>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    config: Res<GameConfig>,
+) {
+    if !input.just_pressed(config.player.key_pause) {
+        return;
+    }
+
+    match state.get() {
+        GameState::Playing => next_state.set(GameState::Paused),
+        GameState::Paused => next_state.set(GameState::Playing),
+        _ => {}
+    }
+}
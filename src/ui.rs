@@ -3,13 +3,61 @@ use bevy_egui::{egui, EguiContexts};
 
 use crate::{
     base::Base,
-    player::{EvPlayerLvlup, ForPlayer, LvlupType, Player},
-    Game, PlayerRes, PLAYER_DEFAULT_CATCHING_RADIUS_MULTIPLIER, PLAYER_DEFAULT_SPEED_MULTIPLIER,
-    PLAYER_KEY_UPGRADE, PLAYER_LVLUP_CATCHING_RADIUS, PLAYER_LVLUP_SPEED,
+    chicken_corral::ChickenCorral,
+    config::GameConfig,
+    player::{player_lvlup, EvPlayerLvlup, ForPlayer, Health, KnockedOut, LvlupType, Player},
+    round::{RoundResult, RoundSummary},
+    save::{EvLoadGame, EvSaveGame},
+    state::{GameState, InRound},
+    werewolf::{EvWerewolfAtCorral, EvWerewolfDeposited},
+    Game, PlayerRes,
 };
 
-#[derive(Event, Default)]
-pub struct EvSpawnPopup;
+pub struct UiPlugin;
+
+impl Plugin for UiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EvSpawnPopup>();
+        app.add_event::<EvWerewolfAtCorral>();
+        app.add_event::<EvWerewolfDeposited>();
+        app.add_event::<EvPlayerLvlup>();
+        app.add_event::<EvSaveGame>();
+        app.add_event::<EvLoadGame>();
+        app.init_resource::<GameConfig>();
+        app.init_resource::<Game>();
+        app.init_resource::<PlayerRes>();
+
+        app.add_systems(OnEnter(InRound), spawn_ui);
+        app.add_systems(
+            Update,
+            // the upgrades are applied in the same frame, so they can be replayed exactly
+            (
+                popup_on_werewolf_at_corral.before(popup),
+                popup_on_werewolf_deposit.before(popup),
+                popup,
+                cleanup_popups,
+                lvl_up_screen.before(player_lvlup),
+                change_health_text,
+            )
+                .run_if(in_state(GameState::Playing)),
+        );
+        app.add_systems(
+            Update,
+            (change_ui).run_if(in_state(GameState::Playing).and_then(resource_changed::<Game>)),
+        );
+        // menus
+        app.add_systems(Update, main_menu.run_if(in_state(GameState::MainMenu)));
+        app.add_systems(Update, pause_menu.run_if(in_state(GameState::Paused)));
+        app.add_systems(
+            Update,
+            game_over_screen.run_if(in_state(GameState::GameOver)),
+        );
+    }
+}
+
+/// The text is shown for a second, when there is no other popup
+#[derive(Event)]
+pub struct EvSpawnPopup(pub String);
 
 #[derive(Event, Default)]
 pub struct ControlLvlupScreen;
@@ -23,6 +71,12 @@ pub struct CatchedChickenScore;
 #[derive(Component)]
 pub struct InventoryChickenScore;
 
+#[derive(Component)]
+pub struct RoundTimeText;
+
+#[derive(Component)]
+pub struct HealthText;
+
 pub fn spawn_ui(mut commands: Commands) {
     commands.spawn((
         TextBundle::from_section(format!("Catched chickens: 0"), TextStyle::default()).with_style(
@@ -36,6 +90,7 @@ pub fn spawn_ui(mut commands: Commands) {
             },
         ),
         CatchedChickenScore,
+        StateScoped(InRound),
     ));
 
     commands.spawn((
@@ -49,17 +104,47 @@ pub fn spawn_ui(mut commands: Commands) {
                 ..Default::default()
             }),
         InventoryChickenScore,
+        StateScoped(InRound),
+    ));
+
+    commands.spawn((
+        TextBundle::from_section("", TextStyle::default()).with_style(Style {
+            position_type: PositionType::Relative,
+            top: Val::Px(50.),
+            justify_self: JustifySelf::Center,
+            ..Default::default()
+        }),
+        RoundTimeText,
+        StateScoped(InRound),
+    ));
+
+    commands.spawn((
+        TextBundle::from_section("", TextStyle::default()).with_style(Style {
+            position_type: PositionType::Relative,
+            top: Val::Px(80.),
+            justify_self: JustifySelf::Center,
+            ..Default::default()
+        }),
+        HealthText,
+        StateScoped(InRound),
     ));
 }
 
+#[allow(clippy::type_complexity)]
 pub fn change_ui(
     player_base_q: Query<&Base, With<ForPlayer>>,
     player_res: Res<PlayerRes>,
+    game: Res<Game>,
+    config: Res<GameConfig>,
     mut catched_score_q: Query<
         &mut Text,
         (With<CatchedChickenScore>, Without<InventoryChickenScore>),
     >,
-    mut inventory_chicken_q: Query<&mut Text, With<InventoryChickenScore>>,
+    mut inventory_chicken_q: Query<
+        &mut Text,
+        (With<InventoryChickenScore>, Without<RoundTimeText>),
+    >,
+    mut round_time_q: Query<&mut Text, (With<RoundTimeText>, Without<CatchedChickenScore>)>,
 ) {
     let mut catched_text = catched_score_q.get_single_mut().unwrap();
     let player_base = player_base_q.get_single().unwrap();
@@ -70,6 +155,35 @@ pub fn change_ui(
         "Chickens in inventory: {}",
         player_res.inventory_chickens_amount
     );
+
+    if let Some(time_limit) = config.round.time_limit {
+        let mut round_time_text = round_time_q.get_single_mut().unwrap();
+        let time_left = (time_limit - game.round_time.elapsed_secs()).max(0.);
+        round_time_text.sections[0].value = format!(
+            "Time left: {}:{:02}",
+            time_left as usize / 60,
+            time_left as usize % 60
+        );
+    }
+}
+
+pub fn change_health_text(
+    player_q: Query<(&Health, Option<&KnockedOut>), With<Player>>,
+    mut health_text_q: Query<&mut Text, With<HealthText>>,
+) {
+    let (Ok((health, knocked_out)), Ok(mut health_text)) =
+        (player_q.get_single(), health_text_q.get_single_mut())
+    else {
+        return;
+    };
+
+    health_text.sections[0].value = match knocked_out {
+        Some(knocked_out) => format!(
+            "Knocked out for {:.0} seconds",
+            knocked_out.0.remaining_secs().ceil()
+        ),
+        None => format!("Health: {:.0}/{:.0}", health.current, health.max),
+    };
 }
 
 pub fn popup(
@@ -77,25 +191,49 @@ pub fn popup(
     mut commands: Commands,
     popups: Query<&Popup>,
 ) {
-    for _ in popup_event.read() {
+    for ev in popup_event.read() {
         if popups.is_empty() {
             commands.spawn((
-                TextBundle::from_section(
-                    format!("The max chickens are catched"),
-                    TextStyle::default(),
-                )
-                .with_style(Style {
+                TextBundle::from_section(ev.0.clone(), TextStyle::default()).with_style(Style {
                     position_type: PositionType::Relative,
                     top: Val::Px(50.),
                     justify_self: JustifySelf::Center,
                     ..Default::default()
                 }),
                 Popup(Timer::from_seconds(1., TimerMode::Once)),
+                StateScoped(InRound),
             ));
         }
     }
 }
 
+// only the raids on the player's corral are interesting for the player
+pub fn popup_on_werewolf_at_corral(
+    mut at_corral_ev: EventReader<EvWerewolfAtCorral>,
+    mut popup_ev: EventWriter<EvSpawnPopup>,
+    p_corral_q: Query<(), (With<ChickenCorral>, With<ForPlayer>)>,
+) {
+    for ev in at_corral_ev.read() {
+        if p_corral_q.contains(ev.corral) {
+            popup_ev.send(EvSpawnPopup(
+                "A werewolf is stealing from your corral".to_string(),
+            ));
+        }
+    }
+}
+
+pub fn popup_on_werewolf_deposit(
+    mut deposited_ev: EventReader<EvWerewolfDeposited>,
+    mut popup_ev: EventWriter<EvSpawnPopup>,
+) {
+    for ev in deposited_ev.read() {
+        popup_ev.send(EvSpawnPopup(format!(
+            "A werewolf brought {} chickens home",
+            ev.chickens
+        )));
+    }
+}
+
 pub fn cleanup_popups(
     mut commands: Commands,
     mut popups: Query<(Entity, &mut Popup)>,
@@ -116,8 +254,9 @@ pub fn lvl_up_screen(
     p_base_q: Query<&Base, With<ForPlayer>>,
     mut player_lvl_up_ev: EventWriter<EvPlayerLvlup>,
     input: Res<ButtonInput<KeyCode>>,
+    config: Res<GameConfig>,
 ) {
-    if input.just_pressed(PLAYER_KEY_UPGRADE) {
+    if input.just_pressed(config.player.key_upgrade) {
         game.lvlup_screen_opened = !game.lvlup_screen_opened;
     }
 
@@ -135,8 +274,8 @@ pub fn lvl_up_screen(
                 }
                 ui.label("Speed lvl:");
                 let player_speed_lvl = 1.
-                    + (player.speed_multiplier - PLAYER_DEFAULT_SPEED_MULTIPLIER)
-                        / PLAYER_LVLUP_SPEED;
+                    + (player.speed_multiplier - config.player.default_speed_multiplier)
+                        / config.player.lvlup_speed;
                 ui.label(format!("{}", player_speed_lvl as usize));
             });
 
@@ -147,9 +286,114 @@ pub fn lvl_up_screen(
                 ui.label("Catching lvl:");
                 let player_catching_lvl = 1.
                     + (player.catching_radius_multiplier
-                        - PLAYER_DEFAULT_CATCHING_RADIUS_MULTIPLIER)
-                        / PLAYER_LVLUP_CATCHING_RADIUS;
+                        - config.player.default_catching_radius_multiplier)
+                        / config.player.lvlup_catching_radius;
                 ui.label(format!("{}", player_catching_lvl as usize));
             });
+
+            ui.horizontal(|ui| {
+                if ui.button("+").clicked() {
+                    player_lvl_up_ev.send(EvPlayerLvlup(LvlupType::Sight));
+                }
+                ui.label("Sight lvl:");
+                let player_sight_lvl = 1.
+                    + (player.sight_multiplier - config.player.default_sight_multiplier)
+                        / config.player.lvlup_sight;
+                ui.label(format!("{}", player_sight_lvl as usize));
+            });
+        });
+}
+
+pub fn main_menu(
+    mut context: EguiContexts,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut load_ev: EventWriter<EvLoadGame>,
+    mut exit_ev: EventWriter<AppExit>,
+) {
+    egui::Window::new("Chicken Catcher")
+        .anchor(egui::Align2::CENTER_CENTER, [0., 0.])
+        .collapsible(false)
+        .resizable(false)
+        .show(context.ctx_mut(), |ui| {
+            if ui.button("Play").clicked() {
+                next_state.set(GameState::Playing);
+            }
+            if ui.button("Load").clicked() {
+                load_ev.send_default();
+            }
+            if ui.button("Editor").clicked() {
+                next_state.set(GameState::Editor);
+            }
+            if ui.button("Quit").clicked() {
+                exit_ev.send(AppExit::Success);
+            }
+        });
+}
+
+pub fn pause_menu(
+    mut context: EguiContexts,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut save_ev: EventWriter<EvSaveGame>,
+    mut load_ev: EventWriter<EvLoadGame>,
+    config: Res<GameConfig>,
+) {
+    egui::Window::new("Pause")
+        .anchor(egui::Align2::CENTER_CENTER, [0., 0.])
+        .collapsible(false)
+        .resizable(false)
+        .show(context.ctx_mut(), |ui| {
+            ui.label(format!("Press {:?} to continue", config.player.key_pause));
+            if ui.button("Continue").clicked() {
+                next_state.set(GameState::Playing);
+            }
+            if ui.button("Save").clicked() {
+                save_ev.send_default();
+            }
+            if ui.button("Load").clicked() {
+                load_ev.send_default();
+            }
+            if ui.button("Give up").clicked() {
+                next_state.set(GameState::GameOver);
+            }
+            if ui.button("Main menu").clicked() {
+                next_state.set(GameState::MainMenu);
+            }
+        });
+}
+
+pub fn game_over_screen(
+    mut context: EguiContexts,
+    mut next_state: ResMut<NextState<GameState>>,
+    summary: Option<Res<RoundSummary>>,
+) {
+    egui::Window::new("Game over")
+        .anchor(egui::Align2::CENTER_CENTER, [0., 0.])
+        .collapsible(false)
+        .resizable(false)
+        .show(context.ctx_mut(), |ui| {
+            // there is no summary if the player gave up
+            if let Some(summary) = summary {
+                ui.heading(match summary.result {
+                    RoundResult::Won => "You won!",
+                    RoundResult::Lost => "You lost!",
+                });
+                ui.label(format!("{:?}", summary.reason));
+                ui.label(format!(
+                    "Round time: {}:{:02}",
+                    summary.time as usize / 60,
+                    summary.time as usize % 60
+                ));
+                ui.label(format!("Your chickens: {}", summary.player_chickens));
+                for (i, chickens) in summary.werewolf_chickens.iter().enumerate() {
+                    ui.label(format!("Werewolf {} chickens: {}", i + 1, chickens));
+                }
+            }
+
+            if ui.button("Play again").clicked() {
+                next_state.set(GameState::Playing);
+            }
+            if ui.button("Main menu").clicked() {
+                next_state.set(GameState::MainMenu);
+            }
         });
 }
@@ -1,18 +1,97 @@
-use bevy::prelude::*;
-use rand::Rng;
+use bevy::{
+    ecs::{
+        entity::{EntityMapper, MapEntities},
+        system::SystemParam,
+    },
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 use crate::{
+    ai::Brain,
     base::Base,
     chicken::Chicken,
-    misc::{get_normilized_dir, get_random_dir},
-    settings::*,
+    chicken_corral::ChickenCorral,
+    config::{config_reloaded, GameConfig},
+    map::MapLayout,
+    navigation::{NavGrid, NavPath},
+    player::{EvBaseLooted, EvPlayerHit, ForPlayer, KnockedOut, Player},
+    rng::GameRng,
+    spawning::{
+        radius_mesh, spawn_chicken_in_corrals, spawn_player_corral,
+        spawn_werewolf_with_base_and_corrals,
+    },
+    state::{GameState, InRound, SpawnSet},
+    werewolf_ai::{werewolf_brain, BaseView, CorralView, WerewolfCtx, WerewolfWorld},
 };
 
+pub struct WerewolfPlugin;
+
+impl Plugin for WerewolfPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>();
+        app.init_resource::<GameRng>();
+        app.init_resource::<MapLayout>();
+        app.init_resource::<NavGrid>();
+        app.add_event::<EvPlayerHit>();
+        app.add_event::<EvBaseLooted>();
+        app.add_event::<EvWerewolfAtCorral>();
+        app.add_event::<EvWerewolfCaughtChicken>();
+        app.add_event::<EvWerewolfDeposited>();
+        app.insert_resource(werewolf_brain());
+
+        // the entities are always spawned in the same order (so the queries are going over them in
+        // the same order), and the behaviour is using the random numbers, so it is ordered after
+        // the other systems, which are using them
+        app.add_systems(
+            OnEnter(InRound),
+            spawn_werewolf_with_base_and_corrals
+                .after(spawn_player_corral)
+                .in_set(SpawnSet::Entities),
+        );
+        app.add_systems(
+            Update,
+            (
+                werewolf_behave.after(spawn_chicken_in_corrals),
+                despawn_caught_chickens.after(werewolf_behave),
+                deposit_werewolf_chickens.after(werewolf_behave),
+                change_werewolf_text,
+            )
+                .run_if(in_state(GameState::Playing)),
+        );
+        app.add_systems(Update, apply_config_to_werewolves.run_if(config_reloaded));
+    }
+}
+
 #[derive(Component)]
 pub struct WerewolfCatchingRadius;
 
-#[derive(Debug, PartialEq, Eq)]
-enum WerewolfBehaviour {
+/// The werewolf got to the fence of the corral, it is catching the chickens from there
+#[derive(Event)]
+pub struct EvWerewolfAtCorral {
+    pub werewolf: Entity,
+    pub corral: Entity,
+}
+
+/// The chicken is despawned after this event (see `despawn_caught_chickens`)
+#[derive(Event)]
+pub struct EvWerewolfCaughtChicken {
+    pub werewolf: Entity,
+    pub chicken: Entity,
+}
+
+/// The werewolf gave the chickens from its inventory to its base (see
+/// `deposit_werewolf_chickens`)
+#[derive(Event)]
+pub struct EvWerewolfDeposited {
+    pub werewolf: Entity,
+    pub base: Entity,
+    pub chickens: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+pub enum WerewolfBehaviour {
     Idle,
     RandomMove,
     MoveToBase,
@@ -20,62 +99,85 @@ enum WerewolfBehaviour {
     // going in there and waiting for chicken to catch
     GoToCorral,
     Catch,
+    // running after the player, who is in the sight
+    Hunt,
+    // the player is near enough to be hit
+    Attack,
+    // sneaking to the things of the player to steal the chickens from there
+    Raid,
+    // running home, the player is looting the base
+    Defend,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+pub enum RaidTarget {
+    // the chickens are taken right from the base
+    Base,
+    // the werewolf is catching the chickens from the fence of the player's corral
+    Corral,
 }
 
 // the base of the werewolf will be stored in this component as entity
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Werewolf {
     pub base: Entity,
     pub base_pos: Vec2,
     pub corral: Option<Entity>,
     pub corral_pos: Option<Vec2>,
-    behaviour: WerewolfBehaviour,
-    move_dir: Option<Vec2>,
-    behaviour_change_timer: Timer,
-    must_change_beh: bool,
-    chickens_in_inventory: usize,
-    in_corral: bool,
-    in_base: bool,
-    catching_try_timer: Timer,
+    pub behaviour: WerewolfBehaviour,
+    /// the direction of the random move
+    pub move_dir: Vec2,
+    pub raid_target: Option<RaidTarget>,
+    pub behaviour_change_timer: Timer,
+    pub chickens_in_inventory: usize,
+    pub catching_try_timer: Timer,
+    pub attack_timer: Timer,
+    pub raid_timer: Timer,
+    /// the player was looting the base, the werewolf is defending it until it is at home
+    #[serde(default)]
+    pub alerted: bool,
+    /// the way around the walls, it is planned again after the load
+    #[serde(skip)]
+    pub path: NavPath,
 }
 
 impl Werewolf {
-    fn change_behaviour_to(&mut self, next_beh: WerewolfBehaviour, werewolf_pos: Option<Vec2>) {
-        self.must_change_beh = false;
-        match next_beh {
-            WerewolfBehaviour::Idle => {
-                self.behaviour = WerewolfBehaviour::Idle;
-                self.move_dir = None;
-            }
-            WerewolfBehaviour::RandomMove => {
-                self.behaviour = WerewolfBehaviour::RandomMove;
-                // we are going from the corral away
-                self.in_corral = false;
-                self.in_base = false;
-                self.move_dir = Some(get_random_dir());
-            }
-            WerewolfBehaviour::Catch => {
-                self.behaviour = WerewolfBehaviour::Catch;
-                self.move_dir = Some(get_random_dir());
-            }
-            WerewolfBehaviour::MoveToBase => {
-                // we are going from the corral away
-                self.in_base = true;
-                self.in_corral = false;
-                self.behaviour = WerewolfBehaviour::MoveToBase;
-                self.move_dir = Some(get_normilized_dir(werewolf_pos.unwrap(), self.base_pos));
-            }
-            WerewolfBehaviour::GoToCorral => {
-                self.in_corral = true;
-                self.in_base = false;
-                self.behaviour = WerewolfBehaviour::GoToCorral;
-                self.move_dir = Some(get_normilized_dir(
-                    werewolf_pos.unwrap(),
-                    self.corral_pos.unwrap(),
-                ));
-            }
+    pub fn new(base: Entity, base_pos: Vec2, config: &GameConfig) -> Self {
+        Self {
+            base,
+            base_pos,
+            corral_pos: None,
+            corral: None,
+            behaviour: WerewolfBehaviour::Idle,
+            move_dir: Vec2::ZERO,
+            raid_target: None,
+            chickens_in_inventory: 0,
+            catching_try_timer: Timer::from_seconds(
+                config.werewolf.catching_try_speed,
+                TimerMode::Repeating,
+            ),
+            behaviour_change_timer: Timer::from_seconds(
+                config.werewolf.behaviour_change_delta,
+                TimerMode::Repeating,
+            ),
+            attack_timer: Timer::from_seconds(config.werewolf.attack_delta, TimerMode::Repeating),
+            raid_timer: Timer::from_seconds(config.werewolf.raid_try_delta, TimerMode::Repeating),
+            alerted: false,
+            path: NavPath::default(),
         }
     }
+
+    /// The werewolf is waiting at the fence for the chickens without moving
+    pub fn is_lurking(&self) -> bool {
+        self.behaviour == WerewolfBehaviour::Catch
+    }
+}
+
+impl MapEntities for Werewolf {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.base = entity_mapper.map_entity(self.base);
+        self.corral = self.corral.map(|corral| entity_mapper.map_entity(corral));
+    }
 }
 
 #[derive(Component)]
@@ -88,33 +190,18 @@ pub struct WerewolfBundle {
 }
 
 impl WerewolfBundle {
-    pub fn default_on_point_with_base(spawnpoint: Vec2, base: Entity) -> Self {
+    pub fn default_on_point_with_base(spawnpoint: Vec2, base: Entity, config: &GameConfig) -> Self {
+        Self::new(Werewolf::new(base, spawnpoint, config), spawnpoint, config)
+    }
+
+    pub fn new(werewolf: Werewolf, spawnpoint: Vec2, config: &GameConfig) -> Self {
         Self {
-            werewolf: Werewolf {
-                base,
-                base_pos: spawnpoint,
-                corral_pos: None,
-                corral: None,
-                behaviour: WerewolfBehaviour::Idle,
-                move_dir: None,
-                in_base: false,
-                must_change_beh: false,
-                chickens_in_inventory: 0,
-                in_corral: false,
-                catching_try_timer: Timer::from_seconds(
-                    WEREWOLF_CATCHING_TRY_SPEED,
-                    TimerMode::Repeating,
-                ),
-                behaviour_change_timer: Timer::from_seconds(
-                    WEREWOLF_BEHAVIOUR_CHANGE_DELTA,
-                    TimerMode::Repeating,
-                ),
-            },
+            werewolf,
             sprite_bundle: SpriteBundle {
-                transform: Transform::from_translation(spawnpoint.extend(WEREWOLF_Z)),
+                transform: Transform::from_translation(spawnpoint.extend(config.werewolf.z)),
                 sprite: Sprite {
-                    color: WEREWOLF_COLOR,
-                    custom_size: Some(Vec2::new(WEREWOLF_SIZE, WEREWOLF_SIZE)),
+                    color: config.werewolf.color,
+                    custom_size: Some(Vec2::new(config.werewolf.size, config.werewolf.size)),
                     ..Default::default()
                 },
                 ..Default::default()
@@ -123,124 +210,136 @@ impl WerewolfBundle {
     }
 }
 
-// rewrite it as events
+/// What happened to the werewolves in the frame
+#[derive(SystemParam)]
+pub struct WerewolfEvents<'w> {
+    hit: EventWriter<'w, EvPlayerHit>,
+    at_corral: EventWriter<'w, EvWerewolfAtCorral>,
+    caught: EventWriter<'w, EvWerewolfCaughtChicken>,
+    deposited: EventWriter<'w, EvWerewolfDeposited>,
+}
+
+impl WerewolfEvents<'_> {
+    fn send(&mut self, world: WerewolfWorld, config: &GameConfig) {
+        self.at_corral.send_batch(world.at_corral);
+        self.caught.send_batch(world.caught);
+        self.deposited.send_batch(world.deposited);
+        for _ in 0..world.hits {
+            self.hit.send(EvPlayerHit {
+                damage: config.werewolf.attack_damage,
+            });
+        }
+    }
+}
+
+/// The werewolves are looking around, then every one of them is thinking with the brain (see
+/// werewolf_ai.rs) and at the end the things they did are put into the world. What happened to
+/// them is sent as events, so the others can react to it
+#[allow(clippy::too_many_arguments)]
 pub fn werewolf_behave(
-    mut commands: Commands,
-    mut werewolf_q: Query<(&mut Transform, &mut Werewolf), Without<Chicken>>,
+    mut werewolf_q: Query<(Entity, &mut Transform, &mut Werewolf), Without<Chicken>>,
     time: Res<Time>,
     chickens_q: Query<(&Transform, Entity), With<Chicken>>,
-    mut bases_q: Query<&mut Base>,
+    mut bases_q: Query<(Entity, &mut Base, &Transform, Has<ForPlayer>), Without<Werewolf>>,
+    corrals_q: Query<(Entity, &Transform, &ChickenCorral, Has<ForPlayer>), Without<Werewolf>>,
+    player_q: Query<(&Transform, Has<KnockedOut>), (With<Player>, Without<Werewolf>)>,
+    mut looted_ev: EventReader<EvBaseLooted>,
+    mut events: WerewolfEvents,
+    brain: Res<Brain<Werewolf>>,
+    nav: Res<NavGrid>,
+    layout: Res<MapLayout>,
+    mut rng: ResMut<GameRng>,
+    config: Res<GameConfig>,
 ) {
-    for (mut w_pos, mut werewolf) in werewolf_q.iter_mut() {
-        // check if werewolf must change behaviour
-        match werewolf.behaviour {
-            // todo! pack this all in werewolfbehaviour impl part, as sentence and result
-            // if beh.sentence {beh.result} or maybe not?????
-            WerewolfBehaviour::Idle | WerewolfBehaviour::RandomMove => {
-                if werewolf
-                    .behaviour_change_timer
-                    .tick(time.delta())
-                    .finished()
-                {
-                    werewolf.must_change_beh = true;
-                }
-            }
-            WerewolfBehaviour::MoveToBase => {
-                if w_pos.translation.xy().distance(werewolf.base_pos) < BASE_CATCHING_RADIUS {
-                    werewolf.must_change_beh = true;
-                }
-            }
-            WerewolfBehaviour::GoToCorral => {
-                if w_pos
-                    .translation
-                    .xy()
-                    .distance(werewolf.corral_pos.unwrap())
-                    <= WEREWOLF_MIN_DISTANCE_TO_CORRAL
-                {
-                    werewolf.must_change_beh = true;
-                }
-            }
-            WerewolfBehaviour::Catch => {
-                if werewolf.chickens_in_inventory == WEREWOLF_MAX_INVENTORY_SPACE {
-                    werewolf.must_change_beh = true;
-                }
-            }
+    let mut world = WerewolfWorld {
+        // the knocked out player is not interesting
+        player: player_q
+            .get_single()
+            .ok()
+            .filter(|(_, knocked_out)| !knocked_out)
+            .map(|(p_pos, _)| p_pos.translation.xy()),
+        bases: bases_q
+            .iter()
+            .map(|(b_ent, base, b_pos, for_player)| BaseView {
+                entity: b_ent,
+                pos: b_pos.translation.xy(),
+                radius: base.radius,
+                chickens_amount: base.chickens_amount,
+                for_player,
+            })
+            .collect(),
+        corrals: corrals_q
+            .iter()
+            .map(|(c_ent, c_pos, corral, for_player)| CorralView {
+                entity: c_ent,
+                rect: corral.rect(c_pos.translation.xy(), &config),
+                for_player,
+            })
+            .collect(),
+        chickens: chickens_q
+            .iter()
+            .map(|(ch_pos, ch_ent)| (ch_ent, ch_pos.translation.xy()))
+            .collect(),
+        looted_bases: looted_ev.read().map(|ev| ev.base).collect(),
+        at_corral: Vec::new(),
+        caught: Vec::new(),
+        deposited: Vec::new(),
+        hits: 0,
+    };
+
+    for (w_ent, mut w_pos, mut werewolf) in werewolf_q.iter_mut() {
+        // the werewolf busy with the player is still remembering it afterwards
+        if world.looted_bases.contains(&werewolf.base) {
+            werewolf.alerted = true;
         }
+        // the raid is tried only once in a while, the time goes also while it is busy
+        werewolf.raid_timer.tick(time.delta());
 
-        // if so, then change it
-        if werewolf.must_change_beh {
-            // if we have some chickens and we are in the base, then give them to base
-            if werewolf.in_base && werewolf.chickens_in_inventory != 0 {
-                let mut base = bases_q.get_mut(werewolf.base).unwrap();
-                base.chickens_amount += werewolf.chickens_in_inventory;
-                werewolf.chickens_in_inventory = 0;
-            }
-
-            // some random behaviour
-            if rand::thread_rng().gen_ratio(1, 10) {
-                werewolf.change_behaviour_to(WerewolfBehaviour::Idle, None);
-            } else if rand::thread_rng().gen_ratio(3, 10) {
-                werewolf.change_behaviour_to(WerewolfBehaviour::RandomMove, None);
-            } else {
-                // go to base as enough chickens were catched
-                if werewolf.chickens_in_inventory == WEREWOLF_MAX_INVENTORY_SPACE {
-                    // consider, that the werewolf will not have move_direction (0,0)
-                    // (if so, it will disappier)
-                    if w_pos.translation.xy() != werewolf.base_pos {
-                        werewolf.change_behaviour_to(
-                            WerewolfBehaviour::MoveToBase,
-                            Some(w_pos.translation.xy()),
-                        );
-                    } else {
-                        werewolf.change_behaviour_to(WerewolfBehaviour::RandomMove, None);
-                    }
-                // go to corral if not
-                } else if !werewolf.in_corral {
-                    if w_pos.translation.xy() != werewolf.corral_pos.unwrap() {
-                        werewolf.change_behaviour_to(
-                            WerewolfBehaviour::GoToCorral,
-                            Some(w_pos.translation.xy()),
-                        );
-                    } else {
-                        werewolf.change_behaviour_to(WerewolfBehaviour::RandomMove, None);
-                    }
-                // else catch some chickens
-                } else {
-                    werewolf.change_behaviour_to(WerewolfBehaviour::Catch, None);
-                }
-            }
+        let pos = w_pos.translation.xy();
+        let mut ctx = WerewolfCtx {
+            entity: w_ent,
+            werewolf: &mut werewolf,
+            pos,
+            velocity: Vec2::ZERO,
+            world: &mut world,
+            delta: time.delta(),
+            nav: &nav,
+            rng: &mut rng,
+            config: &config,
+        };
+        brain.think(&mut ctx);
+
+        let ground_speed = layout.speed_multiplier_at(pos, &config);
+        w_pos.translation += ctx.velocity.extend(0.) * ground_speed * time.delta_seconds();
+    }
+
+    for (b_ent, mut base, _, _) in bases_q.iter_mut() {
+        let Some(view) = world.bases.iter().find(|view| view.entity == b_ent) else {
+            continue;
+        };
+        if base.chickens_amount != view.chickens_amount {
+            base.chickens_amount = view.chickens_amount;
         }
+    }
+    events.send(world, &config);
+}
 
-        // then behave the wolf
-        match werewolf.behaviour {
-            WerewolfBehaviour::RandomMove
-            | WerewolfBehaviour::MoveToBase
-            | WerewolfBehaviour::GoToCorral => {
-                w_pos.translation +=
-                    werewolf.move_dir.unwrap().extend(0.) * WEREWOLF_SPEED * time.delta_seconds()
-            }
-            // todo! if the player thieves chickens the game will crashing, beacuse of despawning
-            WerewolfBehaviour::Catch => {
-                for (ch_pos, ch_ent) in chickens_q.iter() {
-                    // if wolf are ready to catch some chicken
-                    if werewolf
-                        .catching_try_timer
-                        .tick(time.delta())
-                        .just_finished()
-                    {
-                        // try to catch it
-                        if w_pos.translation.xy().distance(ch_pos.translation.xy())
-                            < WEREWOLF_CATCHING_RADIUS
-                        {
-                            werewolf.catching_try_timer.reset();
-                            werewolf.chickens_in_inventory += 1;
-                            commands.entity(ch_ent).despawn();
-                            return;
-                        }
-                    }
-                }
-            }
-            WerewolfBehaviour::Idle => {} // do nothing, this is real idle :)
+pub fn despawn_caught_chickens(
+    mut commands: Commands,
+    mut caught_ev: EventReader<EvWerewolfCaughtChicken>,
+) {
+    for ev in caught_ev.read() {
+        commands.entity(ev.chicken).despawn();
+    }
+}
+
+pub fn deposit_werewolf_chickens(
+    mut bases_q: Query<&mut Base>,
+    mut deposited_ev: EventReader<EvWerewolfDeposited>,
+) {
+    for ev in deposited_ev.read() {
+        if let Ok(mut base) = bases_q.get_mut(ev.base) {
+            base.chickens_amount += ev.chickens;
         }
     }
 }
@@ -269,3 +368,39 @@ pub fn change_werewolf_text(
         text.sections[0].value = chickens_count.to_string();
     }
 }
+
+pub fn apply_config_to_werewolves(
+    mut commands: Commands,
+    mut werewolfs_q: Query<(&mut Werewolf, &mut Sprite)>,
+    radius_q: Query<Entity, With<WerewolfCatchingRadius>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut material: ResMut<Assets<ColorMaterial>>,
+    config: Res<GameConfig>,
+) {
+    for (mut werewolf, mut sprite) in werewolfs_q.iter_mut() {
+        werewolf
+            .catching_try_timer
+            .set_duration(Duration::from_secs_f32(config.werewolf.catching_try_speed));
+        werewolf
+            .attack_timer
+            .set_duration(Duration::from_secs_f32(config.werewolf.attack_delta));
+        werewolf
+            .raid_timer
+            .set_duration(Duration::from_secs_f32(config.werewolf.raid_try_delta));
+        werewolf
+            .behaviour_change_timer
+            .set_duration(Duration::from_secs_f32(
+                config.werewolf.behaviour_change_delta,
+            ));
+        sprite.color = config.werewolf.color;
+        sprite.custom_size = Some(Vec2::new(config.werewolf.size, config.werewolf.size));
+    }
+
+    let mesh = radius_mesh(&mut meshes, config.werewolf.catching_radius);
+    let color = material.add(config.werewolf.catching_radius_color);
+    for radius_ent in radius_q.iter() {
+        commands
+            .entity(radius_ent)
+            .insert((mesh.clone(), color.clone()));
+    }
+}
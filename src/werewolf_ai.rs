@@ -0,0 +1,508 @@
+use bevy::prelude::*;
+use rand::Rng;
+use std::time::Duration;
+
+use crate::{
+    ai::{Agent, Behaviour, Brain, Status, Utility},
+    config::GameConfig,
+    misc::get_random_dir,
+    navigation::NavGrid,
+    rng::GameRng,
+    werewolf::{
+        EvWerewolfAtCorral, EvWerewolfCaughtChicken, EvWerewolfDeposited, RaidTarget, Werewolf,
+        WerewolfBehaviour,
+    },
+};
+
+/// What the werewolves know about the world, it is taken once per frame before they are thinking.
+/// The changes in here are put back into the world after all the werewolves are done (see
+/// `werewolf_behave`)
+pub struct WerewolfWorld {
+    /// the player, who is not knocked out
+    pub player: Option<Vec2>,
+    pub bases: Vec<BaseView>,
+    pub corrals: Vec<CorralView>,
+    /// the chickens, which are not caught yet
+    pub chickens: Vec<(Entity, Vec2)>,
+    /// the player started to loot these bases in this frame
+    pub looted_bases: Vec<Entity>,
+    // what happened in this frame, it is sent as events after all the werewolves are done
+    pub at_corral: Vec<EvWerewolfAtCorral>,
+    pub caught: Vec<EvWerewolfCaughtChicken>,
+    pub deposited: Vec<EvWerewolfDeposited>,
+    pub hits: usize,
+}
+
+pub struct BaseView {
+    pub entity: Entity,
+    pub pos: Vec2,
+    pub radius: f32,
+    pub chickens_amount: usize,
+    pub for_player: bool,
+}
+
+pub struct CorralView {
+    pub entity: Entity,
+    /// the area inside of the walls
+    pub rect: Rect,
+    pub for_player: bool,
+}
+
+impl WerewolfWorld {
+    fn player_base(&self) -> Option<&BaseView> {
+        self.bases.iter().find(|base| base.for_player)
+    }
+
+    fn player_corral(&self) -> Option<&CorralView> {
+        self.corrals.iter().find(|corral| corral.for_player)
+    }
+}
+
+pub struct WerewolfCtx<'a> {
+    pub entity: Entity,
+    pub werewolf: &'a mut Werewolf,
+    pub pos: Vec2,
+    /// where the werewolf is going in this frame, the speed of the ground is not in there
+    pub velocity: Vec2,
+    pub world: &'a mut WerewolfWorld,
+    pub delta: Duration,
+    pub nav: &'a NavGrid,
+    pub rng: &'a mut GameRng,
+    pub config: &'a GameConfig,
+}
+
+impl WerewolfCtx<'_> {
+    fn is_full(&self) -> bool {
+        self.werewolf.chickens_in_inventory >= self.config.werewolf.max_inventory_space
+    }
+
+    fn player_within(&self, distance: f32) -> Option<Vec2> {
+        self.world
+            .player
+            .filter(|p_pos| self.pos.distance(*p_pos) <= distance)
+    }
+
+    fn to_fence(&self, corral: &CorralView) -> f32 {
+        self.pos
+            .distance(self.pos.clamp(corral.rect.min, corral.rect.max))
+    }
+
+    /// The werewolf can't go through the fence, so it is catching the chickens from there
+    fn at_fence(&self, corral: &CorralView) -> bool {
+        self.to_fence(corral) <= self.config.werewolf.min_distance_to_corral
+    }
+
+    fn own_corral(&self) -> Option<&CorralView> {
+        let corral = self.werewolf.corral?;
+        self.world.corrals.iter().find(|view| view.entity == corral)
+    }
+
+    fn home(&self) -> Option<&BaseView> {
+        self.world
+            .bases
+            .iter()
+            .find(|base| base.entity == self.werewolf.base)
+    }
+
+    // the direction is taken every frame, the walls are pushing the werewolf around
+    fn walk_to(&mut self, target: Vec2, speed: f32) {
+        self.velocity = (target - self.pos).try_normalize().unwrap_or_default() * speed;
+    }
+
+    /// Goes on the path around the walls and the obstacles (see navigation.rs)
+    fn travel_to(&mut self, target: Vec2, speed: f32) {
+        let delta = self.delta.as_secs_f32();
+        // even the slowest ground is letting the werewolf move more than this
+        let min_step = speed * delta * 0.1;
+        let next =
+            self.werewolf
+                .path
+                .next_point(self.pos, target, min_step, delta, self.nav, self.config);
+        self.walk_to(next, speed);
+    }
+
+    /// The place in front of the fence on the side facing the werewolf's home, so it is always
+    /// the same one and the path is not planned again and again. The corners are left out, the
+    /// walls are sticking out there
+    fn fence_spot(&self, corral: &CorralView) -> Vec2 {
+        let rect = corral.rect;
+        let home = self.werewolf.base_pos;
+        let to_home = home - rect.center();
+        let inset = Vec2::splat(self.config.corral.wall_length).min(rect.half_size());
+        let along = home.clamp(rect.min + inset, rect.max - inset);
+
+        let (on_fence, outward) =
+            if to_home.x.abs() * rect.height() > to_home.y.abs() * rect.width() {
+                let x = if to_home.x > 0. {
+                    rect.max.x
+                } else {
+                    rect.min.x
+                };
+                (Vec2::new(x, along.y), Vec2::X * to_home.x.signum())
+            } else {
+                let y = if to_home.y > 0. {
+                    rect.max.y
+                } else {
+                    rect.min.y
+                };
+                (Vec2::new(along.x, y), Vec2::Y * to_home.y.signum())
+            };
+        on_fence + outward * self.config.werewolf.min_distance_to_corral / 2.
+    }
+
+    /// Returns the player's base or corral, the empty base is not interesting
+    fn raid_target(&self) -> Option<RaidTarget> {
+        if self
+            .world
+            .player_base()
+            .is_some_and(|base| base.chickens_amount > 0)
+        {
+            Some(RaidTarget::Base)
+        } else {
+            self.world.player_corral().map(|_| RaidTarget::Corral)
+        }
+    }
+
+    fn hit_player(&mut self) {
+        self.world.hits += 1;
+    }
+
+    fn arrive_at(&mut self, corral: Entity) {
+        self.world.at_corral.push(EvWerewolfAtCorral {
+            werewolf: self.entity,
+            corral,
+        });
+    }
+}
+
+impl Agent for Werewolf {
+    type Kind = WerewolfBehaviour;
+    type Ctx<'a> = WerewolfCtx<'a>;
+
+    fn current<'c>(ctx: &'c mut WerewolfCtx<'_>) -> &'c mut WerewolfBehaviour {
+        &mut ctx.werewolf.behaviour
+    }
+
+    fn rng<'c>(ctx: &'c mut WerewolfCtx<'_>) -> &'c mut GameRng {
+        ctx.rng
+    }
+}
+
+/// All the behaviours of the werewolves, the more urgent ones are first
+pub fn werewolf_brain() -> Brain<Werewolf> {
+    let mut brain = Brain::default();
+    brain
+        .add(WerewolfBehaviour::Attack, Attack)
+        .add(WerewolfBehaviour::Hunt, Hunt)
+        .add(WerewolfBehaviour::Defend, Defend)
+        .add(WerewolfBehaviour::Raid, Raid)
+        .add(WerewolfBehaviour::MoveToBase, MoveToBase)
+        .add(WerewolfBehaviour::GoToCorral, GoToCorral)
+        .add(WerewolfBehaviour::Catch, Catch)
+        .add(WerewolfBehaviour::RandomMove, RandomMove)
+        .add(WerewolfBehaviour::Idle, Idle);
+    brain
+}
+
+/// Standing on the place
+struct Idle;
+
+impl Behaviour<Werewolf> for Idle {
+    fn utility(&self, _ctx: &mut WerewolfCtx) -> Utility {
+        Utility::Weight(1)
+    }
+
+    fn enter(&self, ctx: &mut WerewolfCtx) {
+        ctx.werewolf.behaviour_change_timer.reset();
+    }
+
+    fn tick(&self, ctx: &mut WerewolfCtx) -> Status {
+        if ctx
+            .werewolf
+            .behaviour_change_timer
+            .tick(ctx.delta)
+            .just_finished()
+        {
+            Status::Finished
+        } else {
+            Status::Running
+        }
+    }
+}
+
+struct RandomMove;
+
+impl Behaviour<Werewolf> for RandomMove {
+    fn utility(&self, _ctx: &mut WerewolfCtx) -> Utility {
+        Utility::Weight(3)
+    }
+
+    fn enter(&self, ctx: &mut WerewolfCtx) {
+        ctx.werewolf.behaviour_change_timer.reset();
+        ctx.werewolf.move_dir = get_random_dir(ctx.rng);
+    }
+
+    fn tick(&self, ctx: &mut WerewolfCtx) -> Status {
+        ctx.velocity = ctx.werewolf.move_dir * ctx.config.werewolf.speed;
+        Idle.tick(ctx)
+    }
+}
+
+/// Carrying the full paws of chickens home
+struct MoveToBase;
+
+impl Behaviour<Werewolf> for MoveToBase {
+    fn utility(&self, ctx: &mut WerewolfCtx) -> Utility {
+        // the stolen chickens are carried home right away
+        if ctx.werewolf.behaviour == WerewolfBehaviour::Raid
+            && ctx.werewolf.chickens_in_inventory > 0
+        {
+            Utility::Urgent(1)
+        } else if ctx.is_full() {
+            Utility::Weight(6)
+        } else {
+            Utility::None
+        }
+    }
+
+    fn tick(&self, ctx: &mut WerewolfCtx) -> Status {
+        go_home(ctx, ctx.config.werewolf.speed)
+    }
+}
+
+// the chickens in the inventory are given to the base on the arrival (see
+// `deposit_werewolf_chickens`), the base is safe again
+fn go_home(ctx: &mut WerewolfCtx, speed: f32) -> Status {
+    let Some((b_pos, radius)) = ctx.home().map(|base| (base.pos, base.radius)) else {
+        return Status::Finished;
+    };
+    if ctx.pos.distance(b_pos) > radius {
+        ctx.travel_to(b_pos, speed);
+        return Status::Running;
+    }
+
+    ctx.werewolf.alerted = false;
+    let chickens = std::mem::take(&mut ctx.werewolf.chickens_in_inventory);
+    if chickens > 0 {
+        ctx.world.deposited.push(EvWerewolfDeposited {
+            werewolf: ctx.entity,
+            base: ctx.werewolf.base,
+            chickens,
+        });
+    }
+    Status::Finished
+}
+
+struct GoToCorral;
+
+impl Behaviour<Werewolf> for GoToCorral {
+    fn utility(&self, ctx: &mut WerewolfCtx) -> Utility {
+        let at_some_fence = ctx.world.corrals.iter().any(|corral| ctx.at_fence(corral));
+        if !ctx.is_full() && !at_some_fence && ctx.own_corral().is_some() {
+            Utility::Weight(6)
+        } else {
+            Utility::None
+        }
+    }
+
+    fn tick(&self, ctx: &mut WerewolfCtx) -> Status {
+        let Some((c_ent, at_fence, spot)) = ctx
+            .own_corral()
+            .map(|corral| (corral.entity, ctx.at_fence(corral), ctx.fence_spot(corral)))
+        else {
+            return Status::Finished;
+        };
+        if at_fence {
+            ctx.arrive_at(c_ent);
+            return Status::Finished;
+        }
+        ctx.travel_to(spot, ctx.config.werewolf.speed);
+        Status::Running
+    }
+}
+
+/// Waiting at the fence and catching the chickens, which are coming near enough
+struct Catch;
+
+impl Behaviour<Werewolf> for Catch {
+    fn utility(&self, ctx: &mut WerewolfCtx) -> Utility {
+        let at_some_fence = ctx.world.corrals.iter().any(|corral| ctx.at_fence(corral));
+        if !ctx.is_full() && at_some_fence {
+            Utility::Weight(6)
+        } else {
+            Utility::None
+        }
+    }
+
+    fn tick(&self, ctx: &mut WerewolfCtx) -> Status {
+        if ctx.is_full() {
+            return Status::Finished;
+        }
+
+        // if wolf are ready to catch some chicken, it takes the nearest one
+        if ctx
+            .werewolf
+            .catching_try_timer
+            .tick(ctx.delta)
+            .just_finished()
+        {
+            let nearest = ctx
+                .world
+                .chickens
+                .iter()
+                .enumerate()
+                .map(|(i, (_, ch_pos))| (i, ctx.pos.distance(*ch_pos)))
+                .filter(|(_, distance)| *distance < ctx.config.werewolf.catching_radius)
+                .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+            // the other werewolves can't catch the same chicken in this frame
+            if let Some((i, _)) = nearest {
+                let (ch_ent, _) = ctx.world.chickens.remove(i);
+                ctx.world.caught.push(EvWerewolfCaughtChicken {
+                    werewolf: ctx.entity,
+                    chicken: ch_ent,
+                });
+                ctx.werewolf.chickens_in_inventory += 1;
+            }
+        }
+        Status::Running
+    }
+}
+
+/// Running after the player, who is in the sight
+struct Hunt;
+
+impl Behaviour<Werewolf> for Hunt {
+    fn utility(&self, ctx: &mut WerewolfCtx) -> Utility {
+        match ctx.player_within(ctx.config.werewolf.sight_distance) {
+            Some(_) => Utility::Urgent(3),
+            None => Utility::None,
+        }
+    }
+
+    fn tick(&self, ctx: &mut WerewolfCtx) -> Status {
+        let Some(p_pos) = ctx.player_within(ctx.config.werewolf.sight_distance) else {
+            return Status::Finished;
+        };
+        ctx.walk_to(p_pos, ctx.config.werewolf.hunt_speed);
+        Status::Running
+    }
+}
+
+/// The player is near enough to be hit
+struct Attack;
+
+impl Behaviour<Werewolf> for Attack {
+    fn utility(&self, ctx: &mut WerewolfCtx) -> Utility {
+        match ctx.player_within(ctx.config.werewolf.attack_radius) {
+            Some(_) => Utility::Urgent(4),
+            None => Utility::None,
+        }
+    }
+
+    // the first hit is right on the contact
+    fn enter(&self, ctx: &mut WerewolfCtx) {
+        ctx.werewolf.attack_timer.reset();
+        ctx.hit_player();
+    }
+
+    fn tick(&self, ctx: &mut WerewolfCtx) -> Status {
+        if ctx
+            .player_within(ctx.config.werewolf.attack_radius)
+            .is_none()
+        {
+            return Status::Finished;
+        }
+        if ctx.werewolf.attack_timer.tick(ctx.delta).just_finished() {
+            ctx.hit_player();
+        }
+        Status::Running
+    }
+}
+
+/// Running home, the player is looting the base
+struct Defend;
+
+impl Behaviour<Werewolf> for Defend {
+    fn utility(&self, ctx: &mut WerewolfCtx) -> Utility {
+        if ctx.werewolf.alerted {
+            Utility::Urgent(2)
+        } else {
+            Utility::None
+        }
+    }
+
+    // the player is hunted at home, when they are still there
+    fn tick(&self, ctx: &mut WerewolfCtx) -> Status {
+        go_home(ctx, ctx.config.werewolf.hunt_speed)
+    }
+}
+
+/// Sneaking to the things of the player to steal the chickens from there
+struct Raid;
+
+impl Behaviour<Werewolf> for Raid {
+    // the werewolf with the empty paws is sometimes stealing from the player instead
+    fn utility(&self, ctx: &mut WerewolfCtx) -> Utility {
+        if ctx.werewolf.chickens_in_inventory == 0
+            && matches!(
+                ctx.werewolf.behaviour,
+                WerewolfBehaviour::Idle | WerewolfBehaviour::RandomMove | WerewolfBehaviour::Catch
+            )
+            && ctx.werewolf.raid_timer.just_finished()
+            && ctx.rng.gen_bool(ctx.config.werewolf.raid_chance)
+            && ctx.raid_target().is_some()
+        {
+            Utility::Urgent(1)
+        } else {
+            Utility::None
+        }
+    }
+
+    fn enter(&self, ctx: &mut WerewolfCtx) {
+        ctx.werewolf.raid_target = ctx.raid_target();
+    }
+
+    fn tick(&self, ctx: &mut WerewolfCtx) -> Status {
+        match ctx.werewolf.raid_target {
+            Some(RaidTarget::Base) => {
+                let Some((b_pos, radius)) =
+                    ctx.world.player_base().map(|base| (base.pos, base.radius))
+                else {
+                    return Status::Finished;
+                };
+                if ctx.pos.distance(b_pos) > radius {
+                    ctx.travel_to(b_pos, ctx.config.werewolf.speed);
+                    return Status::Running;
+                }
+
+                // the werewolf carries the chickens home, when its paws are full
+                let max = ctx.config.werewolf.max_inventory_space;
+                if let Some(base) = ctx.world.bases.iter_mut().find(|base| base.for_player) {
+                    let stolen = base.chickens_amount.min(max);
+                    base.chickens_amount -= stolen;
+                    ctx.werewolf.chickens_in_inventory += stolen;
+                }
+                Status::Finished
+            }
+            // the werewolf is catching there until its inventory is full
+            Some(RaidTarget::Corral) => {
+                let Some((c_ent, at_fence, spot)) = ctx
+                    .world
+                    .player_corral()
+                    .map(|corral| (corral.entity, ctx.at_fence(corral), ctx.fence_spot(corral)))
+                else {
+                    return Status::Finished;
+                };
+                if at_fence {
+                    ctx.arrive_at(c_ent);
+                    return Status::Finished;
+                }
+                ctx.travel_to(spot, ctx.config.werewolf.speed);
+                Status::Running
+            }
+            // there is nothing to steal from
+            None => Status::Finished,
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use bevy::prelude::*;
+use my_3_game::{
+    config::GameConfig, CameraPlugin, EditorPlugin, GamePlugins, HeadlessPlugin, MinimapPlugin,
+    UiPlugin,
+};
+
+/// The same app as `my_3_game --headless`, the seed is in the given config
+pub fn headless_app(config: GameConfig) -> App {
+    let mut app = App::new();
+    app.insert_resource(config);
+    app.add_plugins(HeadlessPlugin::default());
+    app.add_plugins(
+        GamePlugins
+            .build()
+            .disable::<CameraPlugin>()
+            .disable::<MinimapPlugin>()
+            .disable::<UiPlugin>()
+            .disable::<EditorPlugin>(),
+    );
+    app.finish();
+    app.cleanup();
+    app
+}
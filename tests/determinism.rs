@@ -0,0 +1,53 @@
+mod common;
+
+use bevy::prelude::*;
+use my_3_game::{
+    base::Base, chicken::Chicken, config::GameConfig, headless::simulate, player::Player,
+    werewolf::Werewolf,
+};
+
+const SEED: u64 = 5;
+const TICKS: usize = 1200;
+
+/// the chickens in every base and the positions of everything, what is moving
+type Snapshot = (Vec<(Entity, usize)>, Vec<(Entity, Vec3)>);
+
+fn snapshot(world: &mut World) -> Snapshot {
+    let mut bases: Vec<_> = world
+        .query::<(Entity, &Base)>()
+        .iter(world)
+        .map(|(entity, base)| (entity, base.chickens_amount))
+        .collect();
+    bases.sort_by_key(|(entity, _)| *entity);
+
+    let mut positions: Vec<_> = world
+        .query_filtered::<(Entity, &Transform), Or<(With<Player>, With<Chicken>, With<Werewolf>)>>()
+        .iter(world)
+        .map(|(entity, transform)| (entity, transform.translation))
+        .collect();
+    positions.sort_by_key(|(entity, _)| *entity);
+
+    (bases, positions)
+}
+
+fn run(seed: u64) -> (usize, Snapshot) {
+    let mut app = common::headless_app(GameConfig {
+        seed: Some(seed),
+        ..default()
+    });
+    let ticks = simulate(&mut app, TICKS);
+    (ticks, snapshot(app.world_mut()))
+}
+
+#[test]
+fn same_seed_same_round() {
+    let (first_ticks, first) = run(SEED);
+    let (second_ticks, second) = run(SEED);
+
+    // the round must be really played, not only the empty world compared
+    assert!(!first.0.is_empty(), "no bases were spawned");
+    assert!(!first.1.is_empty(), "nothing is moving");
+    assert_eq!(first_ticks, second_ticks);
+    assert_eq!(first.0, second.0, "the chickens in the bases differ");
+    assert_eq!(first.1, second.1, "the positions differ");
+}
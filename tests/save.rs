@@ -0,0 +1,157 @@
+mod common;
+
+use bevy::prelude::*;
+use my_3_game::{
+    base::Base,
+    chicken::Chicken,
+    chicken_corral::ChickenCorral,
+    config::GameConfig,
+    headless::simulate,
+    player::Player,
+    save::{EvLoadGame, EvSaveGame, SaveGame},
+    werewolf::Werewolf,
+};
+
+const SEED: u64 = 5;
+const TICKS: usize = 600;
+
+/// The entities are found by their positions, the loaded ones have the other ids
+fn translation(world: &World, entity: Entity) -> Vec3 {
+    world
+        .get::<Transform>(entity)
+        .unwrap_or_else(|| panic!("{} is not in the world", entity))
+        .translation
+}
+
+#[test]
+fn loaded_round_is_the_saved_one() {
+    let path = std::env::temp_dir().join(format!("my_3_game_save_test_{}.ron", std::process::id()));
+    let mut config = GameConfig {
+        seed: Some(SEED),
+        ..default()
+    };
+    config.save.path = path.to_string_lossy().into_owned();
+    let mut app = common::headless_app(config);
+    assert_eq!(
+        simulate(&mut app, TICKS),
+        TICKS,
+        "the round is over too early"
+    );
+
+    app.world_mut().send_event(EvSaveGame);
+    app.update();
+    let save = SaveGame::load(&path).expect("the save can't be loaded");
+    // the load is read in one frame and put into the world in the next one
+    app.world_mut().send_event(EvLoadGame);
+    app.update();
+    app.update();
+    let _ = std::fs::remove_file(&path);
+
+    let world = app.world_mut();
+    let old_entities = save
+        .bases
+        .iter()
+        .map(|base| base.entity)
+        .chain(save.corrals.iter().map(|corral| corral.entity))
+        .chain(save.werewolves.iter().map(|werewolf| werewolf.entity))
+        .chain([save.player.entity]);
+    for entity in old_entities {
+        assert!(
+            world.get_entity(entity).is_none(),
+            "{} was not replaced",
+            entity
+        );
+    }
+
+    let saved_translation = |entity: Entity| {
+        save.bases
+            .iter()
+            .map(|base| (base.entity, base.translation))
+            .chain(
+                save.corrals
+                    .iter()
+                    .map(|corral| (corral.entity, corral.translation)),
+            )
+            .chain(save.werewolves.iter().map(|w| (w.entity, w.translation)))
+            .chain([(save.player.entity, save.player.translation)])
+            .find(|(saved, _)| *saved == entity)
+            .map(|(_, translation)| translation)
+            .unwrap()
+    };
+
+    // the werewolves are moving in the frame after the load, so they are found by their homes
+    let werewolves: Vec<Werewolf> = world.query::<&Werewolf>().iter(world).cloned().collect();
+    assert_eq!(werewolves.len(), save.werewolves.len());
+    for saved in save.werewolves.iter() {
+        let werewolf = werewolves
+            .iter()
+            .find(|werewolf| werewolf.base_pos == saved.component.base_pos)
+            .expect("the werewolf is not loaded");
+        assert!(world.get::<Base>(werewolf.base).is_some());
+        assert_eq!(
+            translation(world, werewolf.base),
+            saved_translation(saved.component.base)
+        );
+        let corral = werewolf.corral.expect("the werewolf has no corral");
+        assert!(world.get::<ChickenCorral>(corral).is_some());
+        assert_eq!(
+            translation(world, corral),
+            saved_translation(saved.component.corral.unwrap())
+        );
+    }
+
+    let corrals: Vec<(Vec3, ChickenCorral)> = world
+        .query::<(&Transform, &ChickenCorral)>()
+        .iter(world)
+        .map(|(pos, corral)| (pos.translation, corral.clone()))
+        .collect();
+    assert_eq!(corrals.len(), save.corrals.len());
+    for saved in save.corrals.iter() {
+        let (_, corral) = corrals
+            .iter()
+            .find(|(translation, _)| *translation == saved.translation)
+            .expect("the corral is not loaded");
+        let (Some(owner), Some(saved_owner)) = (corral.belongs_to, saved.component.belongs_to)
+        else {
+            assert!(corral.belongs_to.is_none() && saved.component.belongs_to.is_none());
+            continue;
+        };
+        if saved_owner == save.player.entity {
+            assert!(world.get::<Player>(owner).is_some());
+        } else {
+            let saved_werewolf = save
+                .werewolves
+                .iter()
+                .find(|werewolf| werewolf.entity == saved_owner)
+                .unwrap();
+            let werewolf = world
+                .get::<Werewolf>(owner)
+                .expect("the owner is not a werewolf");
+            assert_eq!(werewolf.base_pos, saved_werewolf.component.base_pos);
+        }
+    }
+
+    let player = world.query::<&Player>().single(world).clone();
+    let p_corral = player.corral.expect("the player has no corral");
+    assert_eq!(
+        translation(world, p_corral),
+        saved_translation(save.player.component.corral.unwrap())
+    );
+
+    let mut bases: Vec<(Vec3, usize)> = world
+        .query::<(&Transform, &Base)>()
+        .iter(world)
+        .map(|(pos, base)| (pos.translation, base.chickens_amount))
+        .collect();
+    let mut saved_bases: Vec<(Vec3, usize)> = save
+        .bases
+        .iter()
+        .map(|base| (base.translation, base.component.chickens_amount))
+        .collect();
+    bases.sort_by(|a, b| a.0.x.total_cmp(&b.0.x).then(a.0.y.total_cmp(&b.0.y)));
+    saved_bases.sort_by(|a, b| a.0.x.total_cmp(&b.0.x).then(a.0.y.total_cmp(&b.0.y)));
+    assert_eq!(bases, saved_bases, "the chickens in the bases differ");
+
+    let chickens = world.query::<&Chicken>().iter(world).count();
+    assert_eq!(chickens, save.chickens.len());
+}